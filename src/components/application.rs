@@ -1,17 +1,41 @@
 use crate::git_operations::pull::{fetch_branches_repository_from_remote, fetch_repository_from_remote};
 use crate::git_operations::repo::{
-    get_files_changed, get_repository, get_repository_active_branch, get_repository_branches,
-    get_repository_tags, git_credentials_callback, is_repository,
+    find_other_checkout, get_repository, get_repository_branches,
+    get_repository_tags, get_repository_worktrees, git_credentials_callback, init_repository,
+    is_repository,
 };
+use crate::git_operations::clone::{clone_repository, folder_name_from_url, CloneEvent, CloneJob};
+use crate::git_operations::commit::{amend, commit, read_commit_message, write_commit_template};
+use crate::git_operations::diff::{apply_hunk, get_diff, DiffView};
+use crate::git_operations::discard::{clean_entries, count_entries, discard_file, get_clean_entries};
+use crate::git_operations::hooks::{run_post_hook, run_pre_push_hook};
+use crate::git_operations::index::{stage_all, stage_file, unstage_all, unstage_file};
+use crate::git_operations::stash::{
+    apply_stash, drop_stash, get_repository_stashes, pop_stash, print_stash_diff, save_stash,
+};
+use crate::git_operations::state::{abort_operation, continue_operation};
+use crate::git_operations::status::{get_repository_status, StatusChange, StatusSection};
+use crate::git_operations::submodule::{
+    get_repository_submodules, init_submodules, sync_submodules, update_submodules,
+};
+use crate::git_operations::tag::create_tag;
 use futures::channel::mpsc::{channel, Receiver, Sender};
-use git2::{PushOptions, ResetType};
+use git2::{BranchType, ObjectType, Oid, PushOptions, ResetType};
 use notify::Event;
-use std::path::{Path};
-use std::process::Command;
+use std::path::{Path, PathBuf};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io;
+use std::process::{Command, ExitStatus};
 use std::string::String;
 use std::fs;
+use crate::config::{RepositorySort, WorkspaceConfig};
+use crate::components::confirm::{Confirm, ConfirmAction};
+use crate::components::diff::Diff;
+use crate::components::filter::RepositoryFilter;
+use crate::session::{SessionState, SESSION_LOG_LINES};
 use crate::components::{
-    items::{GittenRepositoryItem, GittenStringItem},
+    items::{GittenRepositoryItem, GittenRepositoryKind, GittenStashItem, GittenStatusItem, GittenStringItem},
     logs::Logs,
     modes::InputMode,
     selection::Selection,
@@ -23,11 +47,21 @@ pub struct App {
     pub repositories: StatefulList<GittenRepositoryItem>,
     pub branches: StatefulList<GittenStringItem>,
     pub tags: StatefulList<GittenStringItem>,
+    pub status: StatefulList<GittenStatusItem>,
+    pub stashes: StatefulList<GittenStashItem>,
     pub input: String,
     pub input_mode: InputMode,
     pub logs: StatefulList<String>,
     pub path: String,
     pub repository_logs: Option<Logs>,
+    pub diff: Option<Diff>,
+    pub confirm: Option<Confirm>,
+    pub config: WorkspaceConfig,
+    pub clones: Vec<CloneJob>,
+    pub hide_non_repositories: bool,
+    pub sort: RepositorySort,
+    pub filter: RepositoryFilter,
+    pub last_search: String,
     pub channels: (
         Sender<notify::Result<Event>>,
         Receiver<notify::Result<Event>>,
@@ -50,6 +84,8 @@ impl App {
             Selection::Repositories => self.repositories.next(),
             Selection::Tags => self.tags.next(),
             Selection::Branches => self.branches.next(),
+            Selection::Status => self.status.next(),
+            Selection::Stash => self.stashes.next(),
         };
         self.update_repository_details();
     }
@@ -59,6 +95,8 @@ impl App {
             Selection::Repositories => self.repositories.previous(),
             Selection::Tags => self.tags.previous(),
             Selection::Branches => self.branches.previous(),
+            Selection::Status => self.status.previous(),
+            Selection::Stash => self.stashes.previous(),
         }
         self.update_repository_details();
     }
@@ -68,26 +106,26 @@ impl App {
 
         let commands: Vec<String> = input.split_whitespace().map(|f| f.to_owned()).collect();
 
-        if self.get_selected_repository().is_repository && !commands.is_empty() {
+        // A command may open a popup, which changes the mode again
+        self.input_mode = InputMode::Normal;
+        if commands.is_empty() {
+            return;
+        }
+
+        if self.selection == Selection::Repositories && commands[0] == "clone" {
+            self.clone_into_workspace(commands.get(1), commands.get(2));
+        } else if self.repositories.state.selected().is_none() {
+            self.add_log("Repository Should be selected".to_string());
+        } else if self.selection == Selection::Repositories && self.get_selected_repository().is_group() {
+            self.run_group_command(&commands);
+        } else if !self.get_selected_repository().is_repository {
+            match commands[0].as_ref() {
+                "init" => self.init_selected_repository(&commands[1..]),
+                _ => self.add_log("Unknown command!".to_string()),
+            }
+        } else if self.get_selected_repository().is_repository {
             match self.selection {
-                Selection::Repositories => match commands[0].as_ref() {
-                    "co" => {
-                        self.checkout_to_branch(commands.get(1));
-                    }
-                    "tag" => {
-                        self.create_tag(commands.get(1));
-                    }
-                    "rh" => {
-                        self.reset_selected_repository(ResetType::Hard);
-                    }
-                    "pull" => {
-                        self.pull_remote(commands.get(1));
-                    }
-                    "fetch" => {
-                        self.fetch_remote(commands.get(1));
-                    }
-                    _ => self.add_log("Unknown command!".to_string()),
-                },
+                Selection::Repositories => self.run_repository_command(&commands),
                 Selection::Branches => match commands[0].as_ref() {
                     "push" => {
                         self.push_remote(commands.get(1), true);
@@ -100,13 +138,676 @@ impl App {
                     }
                     _ => self.add_log("Unknown command!".to_string()),
                 },
+                Selection::Status => self.add_log("Unknown command!".to_string()),
+                Selection::Stash => match commands[0].as_ref() {
+                    "pop" | "apply" | "drop" => match self.stashes.state.selected() {
+                        Some(i) => self.run_stash_command(&commands[0], Some(self.stashes.items[i].index)),
+                        None => self.add_log("Please select a stash!".to_string()),
+                    },
+                    _ => self.add_log("Unknown command!".to_string()),
+                },
             }
         }
-        self.input_mode = InputMode::Normal;
+    }
+
+    fn run_repository_command(&mut self, commands: &[String]) {
+        if self.get_selected_repository().is_bare() && ["co", "rh", "pull", "sub", "stash", "abort", "continue", "commit", "clean"].contains(&commands[0].as_str()) {
+            self.add_log("Not possible for a bare repository!".to_string());
+            return;
+        }
+
+        match commands[0].as_ref() {
+            "co" => {
+                self.checkout_to_branch(commands.get(1));
+            }
+            "tag" => {
+                self.create_tag(commands.get(1));
+            }
+            "rh" => {
+                self.reset_selected_repository(ResetType::Hard);
+            }
+            "pull" => {
+                self.pull_remote(commands.get(1));
+            }
+            "fetch" => {
+                self.fetch_remote(commands.get(1));
+            }
+            "sub" => {
+                self.run_submodule_command(commands.get(1));
+            }
+            "abort" | "continue" => self.run_operation_command(&commands[0]),
+            "commit" => self.commit(&commands[1..]),
+            "clean" => match commands.get(1).map(|c| c.as_str()) {
+                None => self.preview_clean(false),
+                Some("-x") => self.preview_clean(true),
+                Some(_) => self.add_log("Usage: :clean [-x]".to_string()),
+            },
+            "stash" => match commands.get(1).map(|c| c.as_str()) {
+                Some("pop") | Some("apply") | Some("drop") => {
+                    match commands.get(2).map(|n| n.parse::<usize>()) {
+                        Some(Ok(n)) => self.run_stash_command(&commands[1], Some(n)),
+                        Some(Err(_)) => self.add_log("Stash index must be a number".to_string()),
+                        None => self.run_stash_command(&commands[1], None),
+                    }
+                }
+                _ => self.save_stash(&commands[1..].join(" ")),
+            },
+            _ => self.add_log("Unknown command!".to_string()),
+        }
+    }
+
+    /// Runs a repository command for every repository of the selected group
+    fn run_group_command(&mut self, commands: &[String]) {
+        // Only commands that are safe to repeat on every repository, the others are run on one
+        // repository at a time
+        let allowed = match commands[0].as_str() {
+            "co" | "pull" | "fetch" | "tag" | "sub" => true,
+            "stash" => !matches!(commands.get(1).map(|c| c.as_str()), Some("pop" | "apply" | "drop")),
+            _ => false,
+        };
+        if !allowed {
+            self.add_log(format!("{} can't be run for a group, select a repository!", commands[0]));
+            return;
+        }
+        let selected = self.repositories.state.selected().unwrap();
+        let group = self.repositories.items[selected].group.clone();
+        let members = self.group_members(&group);
+
+        if members.is_empty() {
+            self.add_log("Group has no repositories".to_string());
+        }
+        // `:sub` adds and removes items, each member is looked up again by its path
+        members.into_iter().for_each(|path| {
+            if let Some(i) = self.repositories.items.iter().position(|r| r.path == path) {
+                self.repositories.state.select(Some(i));
+                self.run_repository_command(commands);
+            }
+        });
+        let header = self
+            .repositories
+            .items
+            .iter()
+            .position(|r| r.is_group() && r.group == group);
+        self.repositories.state.select(header.or(Some(selected)));
+    }
+
+    /// Paths of the repositories belonging to a group, their submodules and worktrees left out
+    fn group_members(&self, group: &Option<String>) -> Vec<PathBuf> {
+        self.repositories
+            .items
+            .iter()
+            .filter(|r| !r.is_group() && r.is_repository && r.parent.is_none() && &r.group == group)
+            .map(|r| r.path.clone())
+            .collect()
+    }
+
+    /// Collapses or expands the selected group, or the submodules of the selected repository
+    pub fn toggle_selected_item(&mut self) {
+        if self.repositories.state.selected().is_some() && self.get_selected_repository().is_collapsible() {
+            let item = self.get_selected_repository();
+            item.collapsed = !item.collapsed;
+            self.refresh_visibility();
+        }
+    }
+
+    /// Shows or hides every non repository item
+    pub fn toggle_non_repositories(&mut self) {
+        self.hide_non_repositories = !self.hide_non_repositories;
+        self.refresh_visibility();
+    }
+
+    pub fn set_filter(&mut self, filter: RepositoryFilter) {
+        self.filter = filter;
+        self.refresh_visibility();
+    }
+
+    /// Hides the items of collapsed groups, the submodules of collapsed repositories, the
+    /// non repository items when they are toggled off and the items not matching the filter.
+    /// Group headers are hidden when the filter matches none of their items.
+    pub fn refresh_visibility(&mut self) {
+        let collapsed_groups: Vec<Option<String>> = self
+            .repositories
+            .items
+            .iter()
+            .filter(|r| r.is_group() && r.collapsed)
+            .map(|r| r.group.clone())
+            .collect();
+        let collapsed_parents: Vec<PathBuf> = self
+            .repositories
+            .items
+            .iter()
+            .filter(|r| !r.is_group() && r.collapsed)
+            .map(|r| r.path.clone())
+            .collect();
+
+        let filter = self.filter;
+        let matching_groups: Vec<Option<String>> = self
+            .repositories
+            .items
+            .iter()
+            .filter(|r| !r.is_group() && filter.matches(r))
+            .map(|r| r.group.clone())
+            .collect();
+
+        let hide_non_repositories = self.hide_non_repositories;
+        self.repositories.items.iter_mut().for_each(|r| {
+            if r.is_group() {
+                r.visible = filter == RepositoryFilter::All || matching_groups.contains(&r.group);
+                return;
+            }
+            let in_collapsed_parent = r.is_submodule()
+                && r.parent.as_ref().is_some_and(|p| collapsed_parents.contains(p));
+            let is_hidden_folder = hide_non_repositories && r.kind == GittenRepositoryKind::Folder;
+            r.visible = !collapsed_groups.contains(&r.group)
+                && !in_collapsed_parent
+                && !is_hidden_folder
+                && filter.matches(r);
+        });
+        self.repositories.ensure_visible_selection();
+    }
+
+    /// Aborts or continues the merge, rebase, cherry-pick, revert or bisect in progress
+    fn run_operation_command(&mut self, command: &str) {
+        if let Some(mut repository) = get_repository(&self.get_selected_repository().path) {
+            let result = match command {
+                "abort" => abort_operation(&repository),
+                _ => continue_operation(&mut repository),
+            };
+            match result {
+                Ok(message) => self.add_log(message),
+                Err(e) => self.add_log(format!("Error: {}", e.message())),
+            }
+            self.refresh_selected_repository();
+            self.update_repository_details();
+        }
+    }
+
+    fn save_stash(&mut self, message: &str) {
+        if let Some(mut repository) = get_repository(&self.get_selected_repository().path) {
+            let message = Some(message).filter(|m| !m.is_empty());
+            match save_stash(&mut repository, message) {
+                Ok(message) => self.add_log(message),
+                Err(e) => self.add_log(format!("Error: {}", e.message())),
+            }
+            self.refresh_stashes();
+        }
+    }
+
+    /// Runs pop, apply or drop for a stash entry. Pop and apply default to the latest
+    /// entry, drop needs the entry to be given.
+    fn run_stash_command(&mut self, command: &str, index: Option<usize>) {
+        let mut repository = match get_repository(&self.get_selected_repository().path) {
+            Some(r) => r,
+            None => return,
+        };
+
+        let result = match (command, index) {
+            ("pop", index) => pop_stash(&mut repository, index.unwrap_or(0)),
+            ("apply", index) => apply_stash(&mut repository, index.unwrap_or(0)),
+            ("drop", Some(index)) => drop_stash(&mut repository, index),
+            _ => Err(git2::Error::from_str("Stash index must not be null")),
+        };
+
+        match result {
+            Ok(message) => self.add_log(message),
+            Err(e) => self.add_log(format!("Error: {}", e.message())),
+        }
+        self.refresh_stashes();
+    }
+
+    fn refresh_stashes(&mut self) {
+        let mut repository = get_repository(&self.get_selected_repository().path);
+        self.stashes = StatefulList::builder().items(get_repository_stashes(&mut repository)).build();
+        self.refresh_selected_repository();
+        self.refresh_status_list();
+    }
+
+    /// Commits the index with the given message, or amends HEAD with `--amend`
+    fn commit(&mut self, args: &[String]) {
+        let repository = match get_repository(&self.get_selected_repository().path) {
+            Some(r) => r,
+            None => return,
+        };
+
+        let result = match args.split_first() {
+            Some((flag, message)) if flag == "--amend" => {
+                let message = message.join(" ");
+                amend(&repository, Some(message.as_str()).filter(|m| !m.is_empty()))
+            }
+            Some(_) => commit(&repository, &args.join(" ")),
+            None => Err(git2::Error::from_str("Commit message must not be null")),
+        };
+        match result {
+            Ok(message) => self.add_log(message),
+            Err(e) => self.add_log(format!("Error: {}", e.message())),
+        }
+        self.refresh_selected_repository();
+        self.update_repository_details();
+    }
+
+    /// Writes the commit message template of the selected repository for the editor
+    pub fn prepare_commit_message(&mut self) -> Option<PathBuf> {
+        let item = self.get_selected_repository();
+        if !item.is_repository || item.is_bare() {
+            return None;
+        }
+        match write_commit_template(&get_repository(&item.path)) {
+            Ok(path) => Some(path),
+            Err(e) => {
+                self.add_log(format!("Error: {}", e.message()));
+                None
+            }
+        }
+    }
+
+    /// Commits with the message written in the editor, an empty message aborts the commit
+    pub fn commit_from_editor(&mut self, path: &PathBuf, editor: io::Result<ExitStatus>) {
+        let message = match editor {
+            Ok(status) if status.success() => read_commit_message(path),
+            Ok(_) => Err(git2::Error::from_str("Editor exited with an error, commit is aborted")),
+            Err(e) => Err(git2::Error::from_str(&format!("Could not open the editor: {}", e))),
+        };
+
+        match message {
+            Ok(Some(message)) => self.commit(&[message]),
+            Ok(None) => self.add_log("Commit message is empty, commit is aborted".to_string()),
+            Err(e) => self.add_log(format!("Error: {}", e.message())),
+        }
+    }
+
+    /// Stages or unstages the selected file of the status list, or every file
+    pub fn update_index(&mut self, stage: bool, all: bool) {
+        let item = self.get_selected_repository();
+        if !item.is_repository || item.is_bare() {
+            return;
+        }
+        let repository = match get_repository(&item.path) {
+            Some(r) => r,
+            None => return,
+        };
+
+        let result = if all {
+            if stage { stage_all(&repository) } else { unstage_all(&repository) }
+        } else {
+            let file = match self.status.state.selected() {
+                Some(i) => self.status.items[i].clone(),
+                None => {
+                    self.add_log("Please select a file!".to_string());
+                    return;
+                }
+            };
+            if stage { stage_file(&repository, &file) } else { unstage_file(&repository, &file) }
+        };
+
+        match result {
+            Ok(message) => self.add_log(message),
+            Err(e) => self.add_log(format!("Error: {}", e.message())),
+        }
+        self.refresh_selected_repository();
+        self.refresh_status_list();
+    }
+
+    /// Asks to discard the changes to the selected file of the status list
+    pub fn preview_discard(&mut self) {
+        let item = self.get_selected_repository();
+        if !item.is_repository || item.is_bare() {
+            return;
+        }
+        let file = match self.status.state.selected() {
+            Some(i) => self.status.items[i].clone(),
+            None => {
+                self.add_log("Please select a file!".to_string());
+                return;
+            }
+        };
+        if file.section == StatusSection::Untracked {
+            self.add_log("Untracked files are removed with :clean".to_string());
+            return;
+        }
+
+        let effect = match (file.section, file.change) {
+            (StatusSection::Unstaged, _) => "The unstaged changes are lost, the file is checked out from the index.",
+            (_, StatusChange::New) => "The file is not in HEAD, it is removed from the index and deleted.",
+            _ => "The staged and unstaged changes are lost, the file is checked out from HEAD.",
+        };
+        let lines = vec![format!("{} {}", file.change, file), String::new(), effect.to_string()];
+        let title = format!("Discard changes to {}", file.path);
+        self.confirm = Some(Confirm::new(title, lines, ConfirmAction::Discard(file)));
+        self.input_mode = InputMode::Confirm;
+    }
+
+    /// Lists the untracked files `:clean` would delete, the ignored ones too with `-x`, and asks to
+    /// confirm it
+    fn preview_clean(&mut self, ignored: bool) {
+        let title = format!("Clean {}", self.get_selected_repository().folder_name);
+        let entries = match get_repository(&self.get_selected_repository().path).map(|r| get_clean_entries(&r, ignored)) {
+            Some(Ok(entries)) => entries,
+            Some(Err(e)) => {
+                self.add_log(format!("Error: {}", e.message()));
+                return;
+            }
+            None => return,
+        };
+        if entries.is_empty() {
+            self.add_log("Nothing to clean".to_string());
+            return;
+        }
+
+        let mut lines: Vec<String> = entries.iter().map(|e| e.to_string()).collect();
+        if !ignored {
+            lines.push(String::new());
+            lines.push("Ignored files are kept, :clean -x removes them as well.".to_string());
+        }
+        let title = format!("{}: {} to remove", title, count_entries(&entries));
+        self.confirm = Some(Confirm::new(title, lines, ConfirmAction::Clean(entries)));
+        self.input_mode = InputMode::Confirm;
+    }
+
+    /// Runs the action of the confirm popup on the selected repository
+    pub fn confirm_action(&mut self) {
+        let confirm = match self.confirm.take() {
+            Some(c) => c,
+            None => return,
+        };
+        self.reset_input();
+
+        if let Some(repository) = get_repository(&self.get_selected_repository().path) {
+            let result = match &confirm.action {
+                ConfirmAction::Discard(file) => discard_file(&repository, file),
+                ConfirmAction::Clean(entries) => clean_entries(&repository, entries),
+            };
+            match result {
+                Ok(message) => self.add_log(message),
+                Err(e) => self.add_log(format!("Error: {}", e.message())),
+            }
+            self.refresh_selected_repository();
+            self.refresh_status_list();
+        }
+    }
+
+    pub fn cancel_confirm(&mut self) {
+        self.confirm = None;
+        self.reset_input();
+    }
+
+    /// Opens the diff of the working tree of the selected repository against HEAD
+    pub fn show_diff(&mut self) {
+        let item = self.get_selected_repository();
+        if !item.is_repository || item.is_bare() {
+            self.add_log("Error: a repository with a working tree should be selected".to_string());
+            return;
+        }
+        let title = item.folder_name.clone();
+
+        if let Some(repository) = get_repository(&item.path) {
+            match get_diff(&repository, DiffView::All) {
+                Ok(diff) if diff.lines.is_empty() => self.add_log("Working tree is clean".to_string()),
+                Ok(diff) => {
+                    self.diff = Some(Diff::builder().title(title).diff(diff).build());
+                    self.input_mode = InputMode::Diff;
+                }
+                Err(e) => self.add_log(format!("Error: {}", e.message())),
+            }
+        }
+    }
+
+    /// Switches the diff popup between all, unstaged and staged changes
+    pub fn switch_diff_view(&mut self) {
+        let (title, view, height) = match &self.diff {
+            Some(d) => (d.title.clone(), d.view.next(), d.height),
+            None => return,
+        };
+        if let Some(repository) = get_repository(&self.get_selected_repository().path) {
+            match get_diff(&repository, view) {
+                Ok(diff) => {
+                    let mut diff = Diff::builder().title(title).view(view).diff(diff).build();
+                    diff.height = height;
+                    self.diff = Some(diff);
+                }
+                Err(e) => self.add_log(format!("Error: {}", e.message())),
+            }
+        }
+    }
+
+    /// Stages or unstages the marked lines of the hunk under the cursor, or the whole hunk
+    /// when none of its lines are marked
+    pub fn apply_diff_hunk(&mut self) {
+        let repository = match get_repository(&self.get_selected_repository().path) {
+            Some(r) => r,
+            None => return,
+        };
+        let diff = match &mut self.diff {
+            Some(d) => d,
+            None => return,
+        };
+
+        let result = match diff.current_hunk() {
+            Some(hunk) => apply_hunk(&repository, &diff.diff, diff.view, hunk, &diff.marked_in_current_hunk()),
+            None => Err(git2::Error::from_str("Please select a hunk!")),
+        };
+        if let Ok(reloaded) = get_diff(&repository, diff.view) {
+            diff.reload(reloaded);
+        }
+
+        match result {
+            Ok(message) => self.add_log(message),
+            Err(e) => self.add_log(format!("Error: {}", e.message())),
+        }
+        self.refresh_selected_repository();
+        self.refresh_status_list();
+    }
+
+    pub fn close_diff(&mut self) {
+        self.diff = None;
+        self.reset_input();
+    }
+
+    /// Opens the patch of the selected stash entry in the popup
+    pub fn show_stash_diff(&mut self) {
+        let index = match self.stashes.state.selected() {
+            Some(i) => self.stashes.items[i].index,
+            None => return,
+        };
+
+        if let Some(repository) = get_repository(&self.get_selected_repository().path) {
+            let diff = print_stash_diff(&repository, index).unwrap_or_else(|e| e.message().to_string());
+            self.repository_logs = Some(
+                Logs::builder()
+                    .title(format!("stash@{{{}}}", index))
+                    .logs(diff)
+                    .build(),
+            );
+            self.input_mode = InputMode::Logs;
+        }
+    }
+
+    fn run_submodule_command(&mut self, command: Option<&String>) {
+        let repository = match get_repository(&self.get_selected_repository().path) {
+            Some(r) => r,
+            None => return,
+        };
+
+        let result = match command.map(|c| c.as_str()) {
+            Some("init") => init_submodules(&repository),
+            Some("update") => update_submodules(&repository),
+            Some("sync") => sync_submodules(&repository),
+            _ => {
+                self.add_log("Submodule command must be one of init, update or sync".to_string());
+                return;
+            }
+        };
+
+        match result {
+            Ok(message) => {
+                self.add_log(message);
+                self.refresh_submodules();
+            }
+            Err(e) => self.add_log(format!("Error: {}", e.message())),
+        }
+    }
+
+    /// Recreates the submodule items of the selected repository, nested items have none
+    fn refresh_submodules(&mut self) {
+        let index = self.repositories.state.selected().unwrap();
+        if self.repositories.items[index].parent.is_some() {
+            return;
+        }
+        let parent = self.repositories.items[index].path.clone();
+        self.repositories
+            .items
+            .retain(|r| !(r.is_submodule() && r.parent.as_ref() == Some(&parent)));
+
+        let submodules = AppBuilder::create_submodule_items(
+            &self.config,
+            Path::new(&self.path),
+            &self.repositories.items[index],
+        );
+        self.repositories.items[index].submodules = submodules.len();
+        self.repositories.items.splice(index + 1..index + 1, submodules);
+        self.refresh_visibility();
+    }
+
+    /// Initialises the selected folder as a repository. Arguments are an optional
+    /// `--commit` flag for an initial commit and an optional remote url.
+    fn init_selected_repository(&mut self, arguments: &[String]) {
+        let path = self.get_selected_repository().path.clone();
+        if !path.is_dir() {
+            self.add_log("Only folders can be initialized!".to_string());
+            return;
+        }
+
+        let initial_commit = arguments.iter().any(|a| a == "--commit");
+        let url = arguments.iter().find(|a| *a != "--commit");
+        let remote_name = self.config.default_remote.clone().unwrap_or_else(|| "origin".to_string());
+
+        match init_repository(&path, url.map(|u| (remote_name.as_str(), u.as_str())), initial_commit) {
+            Ok(message) => self.add_log(message),
+            Err(e) => self.add_log(format!("Error: {}", e.message())),
+        }
+        // The repository may exist even if adding the remote or committing failed
+        self.update_application_content(&path);
+        self.update_repository_details();
+    }
+
+    /// Starts cloning `url` into `folder`, or into a folder named after the url, under the root
+    fn clone_into_workspace(&mut self, url: Option<&String>, folder: Option<&String>) {
+        let url = match url {
+            Some(u) => u,
+            None => {
+                self.add_log("Url must not be null".to_string());
+                return;
+            }
+        };
+
+        let folder = folder.cloned().unwrap_or_else(|| folder_name_from_url(url));
+        let path = Path::new(&self.path).join(&folder);
+        if path.exists() {
+            self.add_log(format!("{} already exists!", folder));
+            return;
+        }
+
+        self.add_log(format!("Cloning {} into {}", url, folder));
+        self.clones.push(clone_repository(url, &path));
+    }
+
+    /// Reads the events of the running clones, finished repositories are added to the list
+    pub fn poll_clones(&mut self) {
+        let mut finished = Vec::new();
+        self.clones.iter_mut().enumerate().for_each(|(i, job)| {
+            job.receiver.try_iter().for_each(|event| match event {
+                CloneEvent::Progress { received, total } => job.progress = (received, total),
+                CloneEvent::Done(result) => finished.push((i, result)),
+            });
+        });
+
+        finished.into_iter().rev().for_each(|(i, result)| {
+            let job = self.clones.remove(i);
+            match result {
+                Ok(path) => {
+                    self.add_log(format!("Cloning {} is done!", job.name));
+                    let item = AppBuilder::create_repository_item(&self.config, Path::new(&self.path), &path);
+                    self.insert_repository(item);
+                }
+                Err(e) => self.add_log(format!("Error: Could not clone {}, {}", job.name, e)),
+            }
+        });
+    }
+
+    /// Progress of the running clones to be shown at the bottom
+    pub fn progress_info(&self) -> Option<String> {
+        if self.clones.is_empty() {
+            return None;
+        }
+
+        Some(
+            self.clones
+                .iter()
+                .map(|job| match job.progress {
+                    (_, 0) => format!("Cloning {}...", job.name),
+                    (received, total) => format!(
+                        "Cloning {} {}% ({}/{} objects)",
+                        job.name,
+                        received * 100 / total,
+                        received,
+                        total
+                    ),
+                })
+                .collect::<Vec<String>>()
+                .join(" | "),
+        )
+    }
+
+    /// Adds an item to the list at its sorted position
+    fn insert_repository(&mut self, item: GittenRepositoryItem) {
+        self.regroup_repositories(Some(item));
+    }
+
+    /// Switches between sorting the repositories by name and by their most recent commit
+    pub fn toggle_sort(&mut self) {
+        self.sort = match self.sort {
+            RepositorySort::Name => RepositorySort::Recent,
+            RepositorySort::Recent => RepositorySort::Name,
+        };
+        self.regroup_repositories(None);
+    }
+
+    /// Sorts and groups the list again, adding the given item and keeping the collapsed groups
+    /// and the selection
+    fn regroup_repositories(&mut self, item: Option<GittenRepositoryItem>) {
+        let selected = self
+            .repositories
+            .state
+            .selected()
+            .map(|i| (self.repositories.items[i].path.clone(), self.repositories.items[i].group.clone()));
+        let collapsed_groups: Vec<Option<String>> = self
+            .repositories
+            .items
+            .iter()
+            .filter(|r| r.is_group() && r.collapsed)
+            .map(|r| r.group.clone())
+            .collect();
+
+        let mut content: Vec<GittenRepositoryItem> = self.repositories.items.drain(..).filter(|r| !r.is_group()).collect();
+        content.extend(item);
+        self.repositories.items = AppBuilder::group_application_content(content, self.sort);
+
+        self.repositories.items.iter_mut().for_each(|r| {
+            if r.is_group() && collapsed_groups.contains(&r.group) {
+                r.collapsed = true;
+            }
+        });
+        let position = selected.and_then(|(path, group)| {
+            self.repositories
+                .items
+                .iter()
+                .position(|r| r.path == path && r.group == group)
+        });
+        self.repositories.state.select(position);
+        self.refresh_visibility();
     }
 
     fn push_remote(&mut self, remote: Option<&String>, is_branch: bool) {
-        let remote = match remote {
+        let remote = match self.remote_or_default(remote) {
             Some(b) => b,
             None => {
                 self.add_log("remote name must not be null".to_string());
@@ -136,6 +837,10 @@ impl App {
                 return;
             };
 
+            if let Err(e) = run_pre_push_hook(&repo, &remote, &ref_spec) {
+                self.add_log(format!("Error: {}", e.message()));
+                return;
+            }
             match remote.push(&[&ref_spec], Some(&mut opts)) {
                 Ok(()) => self.add_log("Push is successful!".to_string()),
                 Err(e) => self.add_log("Error: ".to_owned() + e.message()),
@@ -144,7 +849,7 @@ impl App {
     }
 
     fn pull_remote(&mut self, remote: Option<&String>) {
-        let remote = match remote {
+        let remote = match self.remote_or_default(remote) {
             Some(b) => b,
             None => {
                 self.add_log("remote name must not be null".to_string());
@@ -153,7 +858,13 @@ impl App {
         };
 
         let selected_repository = self.get_selected_repository();
-        let branch_name = selected_repository.active_branch_name.as_str().to_owned();
+        let branch_name = match selected_repository.head.as_ref().and_then(|h| h.branch_name()) {
+            Some(b) => b.to_owned(),
+            None => {
+                self.add_log("Error: HEAD is detached, checkout a branch to pull".to_string());
+                return;
+            }
+        };
         let repository = get_repository(&selected_repository.path).unwrap();
 
         match fetch_repository_from_remote(remote.as_str(), branch_name.as_str(), &repository) {
@@ -162,10 +873,11 @@ impl App {
             }
             Err(e) => self.add_log(format!("Error: {}", e.message())),
         };
+        self.refresh_selected_repository();
     }
 
     fn fetch_remote(&mut self, remote: Option<&String>) {
-        let remote = match remote {
+        let remote = match self.remote_or_default(remote) {
             Some(b) => b,
             None => {
                 self.add_log("remote name must not be null".to_string());
@@ -179,12 +891,17 @@ impl App {
         match fetch_branches_repository_from_remote(remote.as_str(), &repository) {
             Ok(message) => {
                 self.add_log(message);
+                self.refresh_selected_repository();
                 self.update_repository_details();
             }
             Err(e) => self.add_log(e.message().to_string()),
         }
     }
 
+    fn remote_or_default(&self, remote: Option<&String>) -> Option<String> {
+        remote.cloned().or_else(|| self.config.default_remote.clone())
+    }
+
     fn checkout_to_branch(&mut self, branch_name: Option<&String>) {
         let branch_name = match branch_name {
             Some(b) => b,
@@ -195,33 +912,53 @@ impl App {
         };
 
         if let Some(repo) = get_repository(&self.get_selected_repository().path) {
-            let head = match repo.head() {
-                Ok(h) => h,
-                Err(_) => {
-                    let sig = repo.signature().unwrap();
-                    let tree_id = {
-                        let mut index = repo.index().unwrap();
-                        index.write_tree().unwrap()
-                    };
-                    let tree = repo.find_tree(tree_id).unwrap();
-                    repo.commit(Some("HEAD"), &sig, &sig, "Initial commit", &tree, &[]).unwrap();
-                    repo.head().unwrap()
+            let branch_name = branch_name.as_str();
+            let reference = format!("refs/heads/{}", branch_name);
+            let previous = repo.head().ok().and_then(|h| h.target());
+
+            // Without a commit to start from the branch is left unborn, like `git switch -c` does
+            if previous.is_none() && repo.find_reference(&reference).is_err() {
+                match repo.set_head(&reference) {
+                    Ok(()) => {
+                        self.refresh_selected_repository();
+                        self.update_repository_details();
+                        self.add_log(format!("HEAD is on {}, which has no commits yet!", branch_name));
+                    }
+                    Err(e) => self.add_log(format!("Error: {}", e.message())),
                 }
-            };
+                return;
+            }
 
-            let oid = head.target().unwrap();
-            let commit = repo.find_commit(oid).unwrap();
-            let branch_name = branch_name.as_str();
-            let _branch = repo.branch(branch_name, &commit, false);
-            let obj = repo
-                .revparse_single(&("refs/heads/".to_owned() + branch_name))
-                .unwrap();
+            // HEAD can't be moved to a branch another worktree is on, the files are left alone then
+            if let Some(path) = find_other_checkout(&repo, &reference) {
+                self.add_log(format!("Error: {} is already checked out in {}", branch_name, path.display()));
+                return;
+            }
+            if let (Some(oid), Err(_)) = (previous, repo.find_branch(branch_name, BranchType::Local)) {
+                if let Err(e) = repo.find_commit(oid).and_then(|c| repo.branch(branch_name, &c, false)) {
+                    self.add_log(format!("Error: {}", e.message()));
+                    return;
+                }
+            }
+            let obj = match repo.revparse_single(&reference) {
+                Ok(obj) => obj,
+                Err(e) => {
+                    self.add_log(format!("Error: {}", e.message()));
+                    return;
+                }
+            };
             match repo.checkout_tree(&obj, None) {
                 Ok(()) => {
-                    let _result = repo.set_head(&("refs/heads/".to_owned() + branch_name));
-                    self.get_selected_repository().active_branch_name = branch_name.to_string();
+                    if let Err(e) = repo.set_head(&reference) {
+                        self.add_log(format!("Error: {}", e.message()));
+                        return;
+                    }
+                    // A branch checkout, which is what the last argument of post-checkout tells
+                    let previous = previous.unwrap_or_else(Oid::zero).to_string();
+                    let note = run_post_hook(&repo, "post-checkout", &[&previous, &obj.id().to_string(), "1"]);
+                    self.refresh_selected_repository();
                     self.update_repository_details();
-                    self.add_log("Checkout is successful!".to_string());
+                    self.add_log(format!("Checkout is successful!{}", note));
                 }
                 Err(e) => self.add_log(format!("Error: {}", e.message())),
             };
@@ -238,21 +975,8 @@ impl App {
         };
 
         if let Some(repo) = get_repository(&self.get_selected_repository().path) {
-            let obj = repo
-                .revparse_single(
-                    &("refs/heads/".to_owned()
-                        + &self.get_selected_repository().active_branch_name),
-                )
-                .unwrap();
-            let sig = repo.signature().unwrap();
-            match repo.tag(
-                tag_name.as_str(),
-                &obj,
-                &sig,
-                format!("Release {}", tag_name).as_str(),
-                true,
-            ) {
-                Ok(_oid) => self.add_log(String::from("Tag creation is successful!")),
+            match create_tag(&repo, tag_name, &format!("Release {}", tag_name)) {
+                Ok(message) => self.add_log(message),
                 Err(e) => self.add_log(format!("Error: {}", e.message())),
             };
             self.update_repository_details();
@@ -260,35 +984,90 @@ impl App {
     }
 
     fn update_repository_details(&mut self) {
-        if self.selection == Selection::Repositories {
+        if self.selection == Selection::Repositories && self.repositories.state.selected().is_some() {
             //Get selected repository
             let rep = get_repository(&self.get_selected_repository().path);
             self.tags.unselect();
             self.tags = StatefulList::builder().items(get_repository_tags(&rep)).build();
             self.branches.unselect();
             self.branches = StatefulList::builder().items(get_repository_branches(&rep)).build();
+            self.status = StatefulList::builder().items(get_repository_status(&rep)).build();
+            let mut rep = rep;
+            self.stashes = StatefulList::builder().items(get_repository_stashes(&mut rep)).build();
         }
     }
 
+    /// Reads the working tree status of the selected repository again, keeping the selected file
+    fn refresh_status_list(&mut self) {
+        let previous = self.status.state.selected();
+        let selected = previous.map(|i| self.status.items[i].clone());
+        let repository = get_repository(&self.get_selected_repository().path);
+        self.status.items = get_repository_status(&repository);
+
+        let position = selected
+            .and_then(|s| self.status.items.iter().position(|f| f == &s))
+            .or_else(|| previous.map(|i| i.min(self.status.items.len().saturating_sub(1))));
+        self.status.state.select(position.filter(|_| !self.status.items.is_empty()));
+    }
+
+    /// Refreshes the items the changed paths belong to, each item once however many of its
+    /// files changed
+    pub fn update_changed_paths(&mut self, paths: Vec<PathBuf>) {
+        let mut changed: Vec<PathBuf> = Vec::new();
+        paths.into_iter().for_each(|path| {
+            // The deepest item holding the path, submodules and worktrees before their parent
+            let item = self
+                .repositories
+                .items
+                .iter()
+                .filter(|r| !r.is_group() && path.starts_with(&r.path))
+                .map(|r| r.path.clone())
+                .max_by_key(|p| p.components().count())
+                .unwrap_or(path);
+            if !changed.contains(&item) {
+                changed.push(item);
+            }
+        });
+        changed.iter().for_each(|path| self.update_application_content(path));
+    }
+
     pub fn update_application_content(&mut self, path: &Path) {
+        let mut commit_changed = false;
         self.repositories.items.iter_mut().for_each(|f| {
-            if path.to_str().unwrap().contains(&f.path.to_str().unwrap()) {
+            if !f.is_group() && path.starts_with(&f.path) {
+                let time = f.last_commit.as_ref().map(|c| c.time);
                 let repository = get_repository(&f.path);
-                let mut is_repository = false;
-                let mut active_branch_name = String::new();
+                let is_repository = repository.is_some();
 
-                if repository.is_some() {
-                    is_repository = true;
-                    active_branch_name = get_repository_active_branch(&repository);
+                if f.kind == GittenRepositoryKind::Folder || f.kind == GittenRepositoryKind::Repository {
+                    f.is_repository = is_repository;
+                    f.kind = if is_repository {
+                        GittenRepositoryKind::Repository
+                    } else {
+                        GittenRepositoryKind::Folder
+                    };
                 }
-
-                let files_changed = get_files_changed(&repository).unwrap_or(0);
-                if f.is_repository != is_repository || f.active_branch_name != active_branch_name || f.files_changed != files_changed {
-                    f.set_active_branch_name(active_branch_name);
-                    f.set_files_changed(files_changed);
-                };
+                f.refresh_status(&repository);
+                commit_changed |= f.last_commit.as_ref().map(|c| c.time) != time;
             }
         });
+
+        if self.sort == RepositorySort::Recent && commit_changed {
+            self.regroup_repositories(None);
+        } else if self.filter != RepositoryFilter::All {
+            self.refresh_visibility();
+        }
+
+        let selected = self.repositories.state.selected();
+        if selected.is_some() && path.starts_with(&self.get_selected_repository().path) {
+            self.refresh_status_list();
+        }
+    }
+
+    /// Reads the status of the selected repository again
+    fn refresh_selected_repository(&mut self) {
+        let repository = get_repository(&self.get_selected_repository().path);
+        self.get_selected_repository().refresh_status(&repository);
     }
 
     pub fn get_selected_repository(&mut self) -> &mut GittenRepositoryItem {
@@ -297,11 +1076,16 @@ impl App {
 
     fn reset_selected_repository(&mut self, reset_type: ResetType) {
         if let Some(r) = get_repository(&self.get_selected_repository().path) {
-            let head = r.head().unwrap();
-            let obj = r.find_object(head.target().unwrap(), None).unwrap();
+            let obj = match r.head().and_then(|h| h.peel(ObjectType::Commit)) {
+                Ok(obj) => obj,
+                Err(_) => {
+                    self.add_log("Error: HEAD has no commit to reset to".to_string());
+                    return;
+                }
+            };
             match r.reset(&obj, reset_type, None) {
                 Ok(()) => {
-                    self.get_selected_repository().files_changed = 0;
+                    self.refresh_selected_repository();
                     self.add_log(String::from("Reset Successful!"))
                 }
                 Err(_) => self.add_log(String::from("Could not reset!")),
@@ -310,45 +1094,97 @@ impl App {
     }
 
     pub fn add_log(&mut self, message: String) {
+        // Output of a failed hook comes after the error, each line as its own entry
+        let mut lines = message.lines();
+        let first = lines.next().unwrap_or_default().to_string();
         if self.repositories.state.selected().is_some() {
             self.logs
                 .items
-                .push(format!("{} - {}", self.get_repository_info(), message));
+                .push(format!("{} - {}", self.get_repository_info(), first));
         } else {
-            self.logs.items.push(message);
+            self.logs.items.push(first);
         }
+        lines.for_each(|l| self.logs.items.push(format!("    {}", l)));
 
         self.logs.state.select(Some(self.logs.items.len()));
     }
 
     fn get_repository_info(&self) -> String {
         let r = &self.repositories.items[self.repositories.state.selected().unwrap()];
-        format!("{} - {}", r.folder_name, r.active_branch_name)
+        match &r.head {
+            Some(head) => format!("{} - {}", r.folder_name, head),
+            None => r.folder_name.clone(),
+        }
     }
 
     pub fn generate_help(&mut self) -> String {
         match self.selection {
             Selection::Repositories => {
-                if self.get_selected_repository().is_repository {
-                    String::from(":co | :tag | :rh | :pull <remote> | :fetch <remote> | l to see the logs | q")
+                if self.get_selected_repository().is_group() {
+                    String::from("enter to collapse/expand | :co | :pull | :fetch | :tag | :sub | :stash for every repository in the group | q")
+                } else if self.get_selected_repository().is_bare() {
+                    String::from(":tag | :fetch <remote> | l to see the logs | q")
+                } else if self.get_selected_repository().is_repository {
+                    String::from(":co | :tag | :rh | :pull <remote> | :fetch <remote> | :sub init/update/sync | :stash [msg]/pop/apply/drop | :abort | :continue | :commit [--amend] <msg> | :clean [-x] | c to commit in $EDITOR | l to see the logs | d to see the diff | q")
                 } else {
-                    String::from("No operation for non repository item | q")
+                    String::from(":init [--commit] [remote-url] | :clone <url> [folder] | q")
                 }
             }
             Selection::Branches => String::from(":push <remote> | q"),
             Selection::Tags => String::from(":push <remote> | q"),
+            Selection::Status => String::from("a/u to stage/unstage the file | A/U for every file | x to discard the file | c to commit | d to see the diff | q"),
+            Selection::Stash => String::from("enter to see the diff | :pop | :apply | :drop | q"),
         }
     }
 
     pub fn search(&mut self) {
+        self.last_search = self.input.clone();
         let input = self.input.as_str();
         match self.selection {
             Selection::Tags => self.tags.search(input),
             Selection::Branches => self.branches.search(input),
+            Selection::Status => self.status.search(input),
+            Selection::Stash => self.stashes.search(input),
             Selection::Repositories => self.repositories.search(input),
         }
     }
 
+    /// Snapshot of the state to be restored when the workspace is opened again
+    pub fn session(&self) -> SessionState {
+        let skip = self.logs.items.len().saturating_sub(SESSION_LOG_LINES);
+        SessionState {
+            selected_repository: self
+                .repositories
+                .state
+                .selected()
+                .map(|i| self.repositories.items[i].path.clone())
+                .filter(|p| !p.as_os_str().is_empty()),
+            selection: self.selection,
+            search: self.last_search.clone(),
+            logs: self.logs.items[skip..].to_vec(),
+        }
+    }
+
+    pub fn restore_session(&mut self, session: SessionState) {
+        self.logs.items = session.logs;
+        if !self.logs.items.is_empty() {
+            self.logs.state.select(Some(self.logs.items.len() - 1));
+        }
+        self.last_search = session.search;
+
+        let selected = session.selected_repository.and_then(|p| {
+            self.repositories
+                .items
+                .iter()
+                .position(|r| r.path == p && r.visible)
+        });
+        if selected.is_some() {
+            self.repositories.state.select(selected);
+            self.update_repository_details();
+            self.selection = session.selection;
+        }
+    }
+
     pub fn reset_input(&mut self) {
         self.input = String::new();
         self.input_mode = InputMode::Normal;
@@ -376,12 +1212,14 @@ impl App {
     }
 }
 
+/// How many folder levels below the root are searched for repositories by default
+pub const DEFAULT_DISCOVERY_DEPTH: usize = 3;
+
 #[derive(Default)]
 pub struct AppBuilder {
-    pub repositories: StatefulList<GittenRepositoryItem>,
-    pub branches: StatefulList<GittenStringItem>,
-    pub tags: StatefulList<GittenStringItem>,
-    pub path: String
+    pub path: String,
+    pub depth: Option<usize>,
+    pub config: WorkspaceConfig,
 }
 
 impl AppBuilder {
@@ -390,55 +1228,279 @@ impl AppBuilder {
         self
     }
 
-    fn generate_application_content(path: &String, content: &mut Vec<GittenRepositoryItem>) {
-        let paths = fs::read_dir(path).unwrap();
+    pub fn depth(mut self, depth: usize) -> AppBuilder {
+        self.depth = Some(depth);
+        self
+    }
+
+    pub fn config(mut self, config: WorkspaceConfig) -> AppBuilder {
+        self.config = config;
+        self
+    }
 
-        paths.for_each(|p| {
-            let dir = p.unwrap();
-            if !dir.file_name().to_str().unwrap().starts_with('.') {
-                let repository = get_repository(&dir.path());
-                let active_branch_name = get_repository_active_branch(&repository);
-                let files_changed = get_files_changed(&repository).unwrap_or(0);
+    /// Walks the folder tree under `path` up to `depth` levels and collects the repositories found.
+    /// A folder containing `.git` is not descended any further. Non repository entries are only
+    /// listed at the root level and when nothing was found below them. Entries matching the
+    /// configured ignore patterns are skipped. Returns whether any repository was found under `path`.
+    fn generate_application_content(
+        config: &WorkspaceConfig,
+        root: &Path,
+        path: &Path,
+        depth: usize,
+        content: &mut Vec<GittenRepositoryItem>,
+    ) -> bool {
+        let mut found_repository = false;
+        let paths = match fs::read_dir(path) {
+            Ok(paths) => paths,
+            Err(_) => return false,
+        };
 
-                let repository_item = GittenRepositoryItem::builder()
-                    .path(fs::canonicalize(&dir.path()).unwrap())
-                    .folder_name(dir.file_name().into_string().unwrap())
-                    .set_is_repository(is_repository(dir.path()))
-                    .files_changed(files_changed)
-                    .active_branch_name(active_branch_name)
-                    .build();
+        paths.flatten().for_each(|dir| {
+            let dir_path = dir.path();
+            if config.is_ignored(dir_path.strip_prefix(root).unwrap_or(&dir_path), dir_path.is_dir()) {
+                return;
+            }
 
-                content.push(repository_item);
+            if is_repository(dir_path.clone()) {
+                content.push(AppBuilder::create_repository_item(config, root, &dir_path));
+                found_repository = true;
+            } else if depth > 1
+                && dir_path.is_dir()
+                && AppBuilder::generate_application_content(config, root, &dir_path, depth - 1, content)
+            {
+                found_repository = true;
+            } else if path == root {
+                content.push(AppBuilder::create_repository_item(config, root, &dir_path));
             }
         });
+
+        found_repository
+    }
+
+    fn create_repository_item(config: &WorkspaceConfig, root: &Path, path: &Path) -> GittenRepositoryItem {
+        let repository = get_repository(&path.to_path_buf());
+        let relative_path = path.strip_prefix(root).unwrap_or(path);
+        let folder_name = relative_path.to_string_lossy().to_string();
+        let kind = match &repository {
+            Some(r) if r.is_bare() => GittenRepositoryKind::Bare,
+            Some(r) if r.is_worktree() => GittenRepositoryKind::Worktree,
+            Some(_) => GittenRepositoryKind::Repository,
+            None => GittenRepositoryKind::Folder,
+        };
+
+        let mut item = GittenRepositoryItem::builder()
+            .path(fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()))
+            .folder_name(folder_name)
+            .set_is_repository(repository.is_some())
+            .kind(kind)
+            .group(config.group_for(relative_path))
+            .show_summary(config.commit_summary)
+            .build();
+        item.refresh_status(&repository);
+        item
+    }
+
+    /// Adds the linked worktrees of every repository as its nested items. Worktrees found by
+    /// the discovery are replaced by the nested ones, so they are only listed once.
+    fn link_worktrees(config: &WorkspaceConfig, root: &Path, content: &mut Vec<GittenRepositoryItem>) {
+        let mut worktrees: Vec<GittenRepositoryItem> = Vec::new();
+
+        content
+            .iter()
+            .filter(|r| r.kind == GittenRepositoryKind::Repository || r.is_bare())
+            .for_each(|r| {
+                get_repository_worktrees(&get_repository(&r.path)).iter().for_each(|w| {
+                    let mut worktree = AppBuilder::create_repository_item(config, root, w);
+                    worktree.group = r.group.clone();
+                    worktree.parent = Some(r.path.clone());
+                    worktrees.push(worktree);
+                });
+            });
+
+        content.retain(|r| !worktrees.iter().any(|w| w.path == r.path));
+        content.append(&mut worktrees);
+    }
+
+    fn create_submodule_items(config: &WorkspaceConfig, root: &Path, parent: &GittenRepositoryItem) -> Vec<GittenRepositoryItem> {
+        get_repository_submodules(&get_repository(&parent.path))
+            .into_iter()
+            .map(|s| {
+                let mut submodule = AppBuilder::create_repository_item(config, root, &s.path);
+                submodule.folder_name = s.name;
+                submodule.kind = GittenRepositoryKind::Submodule;
+                submodule.submodule_state = Some(s.state);
+                submodule.group = parent.group.clone();
+                submodule.parent = Some(parent.path.clone());
+                submodule
+            })
+            .collect()
+    }
+
+    /// Adds the submodules of every top level repository as its nested items, repositories with
+    /// submodules start collapsed. The list is only nested one level deep, so the submodules of
+    /// a nested worktree are not listed.
+    fn link_submodules(config: &WorkspaceConfig, root: &Path, content: &mut Vec<GittenRepositoryItem>) {
+        let mut submodules: Vec<GittenRepositoryItem> = Vec::new();
+
+        content
+            .iter_mut()
+            .filter(|r| r.kind == GittenRepositoryKind::Repository || r.kind == GittenRepositoryKind::Worktree)
+            .filter(|r| r.parent.is_none())
+            .for_each(|r| {
+                let mut items = AppBuilder::create_submodule_items(config, root, r);
+                r.submodules = items.len();
+                r.collapsed = r.submodules > 0;
+                submodules.append(&mut items);
+            });
+
+        content.retain(|r| !submodules.iter().any(|s| s.path == r.path));
+        content.append(&mut submodules);
+    }
+
+    /// Sorts the items by group, then by name or most recent commit first, placing a header row
+    /// before each group and the nested items right after their parent. Items without a group
+    /// come first, sorted by most recent commit the groups are ordered by their newest item.
+    fn group_application_content(content: Vec<GittenRepositoryItem>, sort: RepositorySort) -> Vec<GittenRepositoryItem> {
+        let (mut children, mut content): (Vec<GittenRepositoryItem>, Vec<GittenRepositoryItem>) =
+            content.into_iter().partition(|r| r.parent.is_some());
+        let time = |r: &GittenRepositoryItem| r.last_commit.as_ref().map(|c| c.time);
+        let mut newest: HashMap<Option<String>, Option<i64>> = HashMap::new();
+        content.iter().for_each(|r| {
+            let group = newest.entry(r.group.clone()).or_default();
+            *group = (*group).max(time(r));
+        });
+
         content.sort_by(|a, b| {
-            a.folder_name
-                .to_lowercase()
-                .cmp(&b.folder_name.to_lowercase())
+            let (group_recency, recency) = match sort {
+                RepositorySort::Name => (Ordering::Equal, Ordering::Equal),
+                // Items without commits go last
+                RepositorySort::Recent => (newest[&b.group].cmp(&newest[&a.group]), time(b).cmp(&time(a))),
+            };
+            a.group
+                .is_some()
+                .cmp(&b.group.is_some())
+                .then(group_recency)
+                .then_with(|| a.group.cmp(&b.group))
+                .then(recency)
+                .then_with(|| a.folder_name.to_lowercase().cmp(&b.folder_name.to_lowercase()))
         });
+        children.sort_by_key(|a| a.folder_name.to_lowercase());
+
+        let mut grouped: Vec<GittenRepositoryItem> = Vec::with_capacity(content.len());
+        content.into_iter().for_each(|item| {
+            let is_new_group = match grouped.last() {
+                Some(last) => last.group != item.group,
+                None => true,
+            };
+            if let Some(group) = item.group.as_ref().filter(|_| is_new_group) {
+                grouped.push(GittenRepositoryItem::group_header(group.clone()));
+            }
+            let path = item.path.clone();
+            grouped.push(item);
+
+            let (mut nested, rest): (Vec<GittenRepositoryItem>, Vec<GittenRepositoryItem>) =
+                children.drain(..).partition(|c| c.parent.as_ref() == Some(&path));
+            children = rest;
+            grouped.append(&mut nested);
+        });
+        grouped
     }
 
     pub fn build(self) -> App {
         let mut content = Vec::new();
+        let root = fs::canonicalize(&self.path).unwrap_or_else(|_| PathBuf::from(&self.path));
 
-        AppBuilder::generate_application_content(&self.path, &mut content);
+        AppBuilder::generate_application_content(
+            &self.config,
+            &root,
+            &root,
+            self.depth.or(self.config.depth).unwrap_or(DEFAULT_DISCOVERY_DEPTH),
+            &mut content,
+        );
+        self.config.repository_paths(&root).iter().for_each(|p| {
+            let path = fs::canonicalize(p).unwrap_or_else(|_| p.to_path_buf());
+            if p.exists() && !content.iter().any(|c| c.path == path) {
+                content.push(AppBuilder::create_repository_item(&self.config, &root, p));
+            }
+        });
+        AppBuilder::link_worktrees(&self.config, &root, &mut content);
+        AppBuilder::link_submodules(&self.config, &root, &mut content);
+        let content = AppBuilder::group_application_content(content, self.config.sort);
 
         let (tx, rx): (
             Sender<notify::Result<Event>>,
             Receiver<notify::Result<Event>>,
         ) = channel(1);
 
-        App {
+        let mut app = App {
             selection: Selection::Repositories,
             repositories: StatefulList::builder().items(content).build(),
             branches: StatefulList::builder().items(vec![]).build(),
             tags: StatefulList::builder().items(vec![]).build(),
+            status: StatefulList::builder().items(vec![]).build(),
+            stashes: StatefulList::builder().items(vec![]).build(),
             input: String::new(),
             input_mode: InputMode::Normal,
             logs: StatefulList::builder().items(vec![]).build(),
             repository_logs: None,
-            path: self.path,
+            diff: None,
+            confirm: None,
+            path: root.to_string_lossy().to_string(),
+            hide_non_repositories: self.config.hide_non_repositories,
+            sort: self.config.sort,
+            filter: RepositoryFilter::All,
+            config: self.config,
+            clones: Vec::new(),
+            last_search: String::new(),
             channels: (tx, rx),
-        }
+        };
+        app.refresh_visibility();
+        app
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git_operations::repo::LastCommit;
+
+    fn item(name: &str, group: Option<&str>, time: Option<i64>) -> GittenRepositoryItem {
+        let mut item = GittenRepositoryItem::builder()
+            .path(PathBuf::from(name))
+            .folder_name(name.to_string())
+            .set_is_repository(true)
+            .group(group.map(|g| g.to_string()))
+            .build();
+        item.last_commit = time.map(|time| LastCommit {
+            time,
+            ..LastCommit::default()
+        });
+        item
+    }
+
+    fn names(items: &[GittenRepositoryItem]) -> Vec<String> {
+        items.iter().map(|r| r.to_string()).collect()
+    }
+
+    #[test]
+    fn groups_are_ordered_by_their_newest_repository() {
+        let content = || vec![
+            item("alpha/old", Some("alpha"), Some(100)),
+            item("alpha/none", Some("alpha"), None),
+            item("beta/new", Some("beta"), Some(300)),
+            item("beta/older", Some("beta"), Some(50)),
+            item("loose", None, Some(10)),
+        ];
+
+        let by_name = AppBuilder::group_application_content(content(), RepositorySort::Name);
+        let by_recency = AppBuilder::group_application_content(content(), RepositorySort::Recent);
+        assert_eq!(
+            names(&by_name),
+            ["loose", "alpha", "alpha/none", "alpha/old", "beta", "beta/new", "beta/older"]
+        );
+        assert_eq!(
+            names(&by_recency),
+            ["loose", "beta", "beta/new", "beta/older", "alpha", "alpha/old", "alpha/none"]
+        );
     }
 }
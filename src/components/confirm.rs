@@ -0,0 +1,37 @@
+use crate::git_operations::discard::CleanEntry;
+use crate::git_operations::status::FileStatus;
+
+/// Destructive action waiting to be confirmed
+pub enum ConfirmAction {
+    Discard(FileStatus),
+    Clean(Vec<CleanEntry>),
+}
+
+/// Popup previewing what a destructive action is going to do, until it is confirmed or cancelled
+pub struct Confirm {
+    pub title: String,
+    pub lines: Vec<String>,
+    pub action: ConfirmAction,
+    pub offset: u16,
+}
+
+impl Confirm {
+    pub fn new(title: String, lines: Vec<String>, action: ConfirmAction) -> Confirm {
+        Confirm {
+            title,
+            lines,
+            action,
+            offset: 0,
+        }
+    }
+
+    pub fn scroll_down(&mut self) {
+        if (self.offset as usize) + 1 < self.lines.len() {
+            self.offset += 1;
+        }
+    }
+
+    pub fn scroll_up(&mut self) {
+        self.offset = self.offset.saturating_sub(1);
+    }
+}
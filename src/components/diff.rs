@@ -0,0 +1,196 @@
+use crate::git_operations::diff::{DiffLineKind, DiffView, WorkingTreeDiff};
+use tui::widgets::ListState;
+
+/// Diff shown in the diff popup with its cursor, the lines marked for staging and the file
+/// selected in the jump list
+pub struct Diff {
+    pub title: String,
+    pub view: DiffView,
+    pub diff: WorkingTreeDiff,
+    pub cursor: usize,
+    /// Added or removed lines marked to be staged or unstaged instead of their whole hunk
+    pub marked: Vec<usize>,
+    /// First line shown and how far the lines are scrolled to the right
+    pub offset: (usize, u16),
+    /// Number of lines the diff was last drawn with, the cursor is kept within them
+    pub height: u16,
+    pub files: ListState,
+}
+
+impl Diff {
+    pub fn builder() -> DiffBuilder {
+        DiffBuilder::default()
+    }
+
+    /// Replaces the diff after the index changed, keeping the cursor where it was
+    pub fn reload(&mut self, diff: WorkingTreeDiff) {
+        self.diff = diff;
+        self.marked.clear();
+        self.move_cursor_to(self.cursor);
+    }
+
+    pub fn move_down(&mut self, lines: usize) {
+        self.move_cursor_to(self.cursor + lines);
+    }
+
+    pub fn move_up(&mut self, lines: usize) {
+        self.move_cursor_to(self.cursor.saturating_sub(lines));
+    }
+
+    /// Scrolls to the right, as long as the longest line has something left to show
+    pub fn scroll_right(&mut self) {
+        let longest = self.diff.lines.iter().map(|l| l.text.chars().count()).max().unwrap_or(0);
+        let last_column = u16::try_from(longest.saturating_sub(1)).unwrap_or(u16::MAX);
+        self.offset.1 = self.offset.1.saturating_add(4).min(last_column);
+    }
+
+    pub fn scroll_left(&mut self) {
+        self.offset.1 = self.offset.1.saturating_sub(4);
+    }
+
+    /// Jumps to the header of the next file
+    pub fn next_file(&mut self) {
+        let next = self.files.selected().map_or(0, |i| i + 1);
+        if let Some(file) = self.diff.files.get(next) {
+            self.move_cursor_to(file.line);
+        }
+    }
+
+    /// Jumps to the header of the previous file
+    pub fn previous_file(&mut self) {
+        let previous = self.files.selected().map_or(0, |i| i.saturating_sub(1));
+        if let Some(file) = self.diff.files.get(previous) {
+            self.move_cursor_to(file.line);
+        }
+    }
+
+    pub fn next_hunk(&mut self) {
+        if let Some(hunk) = self.diff.hunks.iter().find(|h| h.line > self.cursor) {
+            self.move_cursor_to(hunk.line);
+        }
+    }
+
+    pub fn previous_hunk(&mut self) {
+        if let Some(hunk) = self.diff.hunks.iter().rev().find(|h| h.line < self.cursor) {
+            self.move_cursor_to(hunk.line);
+        }
+    }
+
+    /// Hunk the cursor is in
+    pub fn current_hunk(&self) -> Option<usize> {
+        self.diff.lines.get(self.cursor).and_then(|l| l.hunk)
+    }
+
+    /// Marks or unmarks the added or removed line under the cursor
+    pub fn toggle_mark(&mut self) {
+        let is_change = self
+            .diff
+            .lines
+            .get(self.cursor)
+            .is_some_and(|l| l.kind == DiffLineKind::Added || l.kind == DiffLineKind::Removed);
+        if !is_change {
+            return;
+        }
+        match self.marked.iter().position(|m| *m == self.cursor) {
+            Some(i) => {
+                self.marked.remove(i);
+            }
+            None => self.marked.push(self.cursor),
+        }
+        self.move_down(1);
+    }
+
+    /// Marked lines of the hunk the cursor is in
+    pub fn marked_in_current_hunk(&self) -> Vec<usize> {
+        let hunk = self.current_hunk();
+        self.marked
+            .iter()
+            .copied()
+            .filter(|m| hunk.is_some() && self.diff.lines[*m].hunk == hunk)
+            .collect()
+    }
+
+    /// Moves the cursor, scrolling to keep it visible and selecting its file in the jump list
+    fn move_cursor_to(&mut self, line: usize) {
+        self.cursor = line.min(self.diff.lines.len().saturating_sub(1));
+        let cursor = self.cursor;
+        let height = usize::from(self.height.max(1));
+        if cursor < self.offset.0 {
+            self.offset.0 = cursor;
+        } else if cursor >= self.offset.0 + height {
+            self.offset.0 = cursor + 1 - height;
+        }
+
+        let current = self.diff.files.iter().rposition(|f| f.line <= self.cursor);
+        self.files.select(current.or(Some(0)).filter(|_| !self.diff.files.is_empty()));
+    }
+}
+
+#[derive(Default)]
+pub struct DiffBuilder {
+    pub title: String,
+    pub view: DiffView,
+    pub diff: WorkingTreeDiff,
+}
+
+impl DiffBuilder {
+    pub fn title(mut self, title: String) -> DiffBuilder {
+        self.title = title;
+        self
+    }
+
+    pub fn view(mut self, view: DiffView) -> DiffBuilder {
+        self.view = view;
+        self
+    }
+
+    pub fn diff(mut self, diff: WorkingTreeDiff) -> DiffBuilder {
+        self.diff = diff;
+        self
+    }
+
+    pub fn build(self) -> Diff {
+        let mut diff = Diff {
+            title: self.title,
+            view: self.view,
+            diff: self.diff,
+            cursor: 0,
+            marked: Vec::new(),
+            offset: (0, 0),
+            height: 0,
+            files: ListState::default(),
+        };
+        diff.move_cursor_to(0);
+        diff
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git_operations::diff::DiffLine;
+
+    #[test]
+    fn scrolling_right_stops_at_the_longest_line() {
+        let line = |text: &str| DiffLine {
+            kind: DiffLineKind::Added,
+            origin: '+',
+            text: text.to_string(),
+            hunk: None,
+        };
+        let lines = vec![line("+short"), line(&format!("+{}", "x".repeat(9)))];
+        let mut diff = Diff::builder()
+            .diff(WorkingTreeDiff {
+                lines,
+                ..WorkingTreeDiff::default()
+            })
+            .build();
+
+        diff.scroll_right();
+        assert_eq!(diff.offset.1, 4);
+        (0..100_000).for_each(|_| diff.scroll_right());
+        assert_eq!(diff.offset.1, 9);
+        diff.scroll_left();
+        assert_eq!(diff.offset.1, 5);
+    }
+}
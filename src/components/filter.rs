@@ -0,0 +1,137 @@
+use crate::components::items::GittenRepositoryItem;
+use crate::git_operations::repo::Head;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+/// Filter of the repository list, the items not matching it are hidden
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RepositoryFilter {
+    #[default]
+    All,
+    Dirty,
+    AheadBehind,
+    Detached,
+    Stashes,
+    NonDefaultBranch,
+}
+
+impl RepositoryFilter {
+    /// Filter bound to a number key
+    pub fn from_key(key: char) -> Option<RepositoryFilter> {
+        match key {
+            '0' => Some(RepositoryFilter::All),
+            '1' => Some(RepositoryFilter::Dirty),
+            '2' => Some(RepositoryFilter::AheadBehind),
+            '3' => Some(RepositoryFilter::Detached),
+            '4' => Some(RepositoryFilter::Stashes),
+            '5' => Some(RepositoryFilter::NonDefaultBranch),
+            _ => None,
+        }
+    }
+
+    pub fn matches(&self, item: &GittenRepositoryItem) -> bool {
+        match self {
+            RepositoryFilter::All => true,
+            _ if !item.is_repository => false,
+            RepositoryFilter::Dirty => item.changes.is_dirty(),
+            RepositoryFilter::AheadBehind => item.upstream.is_some_and(|(ahead, behind)| ahead > 0 || behind > 0),
+            RepositoryFilter::Detached => matches!(item.head, Some(Head::Detached { .. })),
+            RepositoryFilter::Stashes => item.stashes > 0,
+            RepositoryFilter::NonDefaultBranch => match (&item.head, &item.default_branch) {
+                (Some(Head::Branch(name)), Some(default)) => name != default,
+                _ => false,
+            },
+        }
+    }
+}
+
+impl Display for RepositoryFilter {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                RepositoryFilter::All => "all",
+                RepositoryFilter::Dirty => "dirty",
+                RepositoryFilter::AheadBehind => "ahead/behind",
+                RepositoryFilter::Detached => "detached",
+                RepositoryFilter::Stashes => "with stashes",
+                RepositoryFilter::NonDefaultBranch => "not on default branch",
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git_operations::status::StatusCounts;
+    use std::path::PathBuf;
+
+    fn repository() -> GittenRepositoryItem {
+        let mut item = GittenRepositoryItem::builder()
+            .path(PathBuf::from("repo"))
+            .folder_name("repo".to_string())
+            .set_is_repository(true)
+            .build();
+        item.head = Some(Head::Branch("main".to_string()));
+        item.default_branch = Some("main".to_string());
+        item
+    }
+
+    fn matching(item: &GittenRepositoryItem) -> Vec<RepositoryFilter> {
+        ('0'..='5')
+            .filter_map(RepositoryFilter::from_key)
+            .filter(|f| f.matches(item))
+            .collect()
+    }
+
+    #[test]
+    fn clean_repository_only_matches_all() {
+        assert_eq!(matching(&repository()), [RepositoryFilter::All]);
+    }
+
+    #[test]
+    fn non_repository_only_matches_all() {
+        let mut item = repository();
+        item.is_repository = false;
+        item.stashes = 1;
+        item.changes.untracked = 1;
+        assert_eq!(matching(&item), [RepositoryFilter::All]);
+    }
+
+    #[test]
+    fn repository_matches_each_filter_it_qualifies_for() {
+        let mut dirty = repository();
+        dirty.changes = StatusCounts { untracked: 1, ..StatusCounts::default() };
+        assert_eq!(matching(&dirty), [RepositoryFilter::All, RepositoryFilter::Dirty]);
+
+        let mut behind = repository();
+        behind.upstream = Some((0, 2));
+        assert_eq!(matching(&behind), [RepositoryFilter::All, RepositoryFilter::AheadBehind]);
+        behind.upstream = Some((0, 0));
+        assert_eq!(matching(&behind), [RepositoryFilter::All]);
+
+        let mut stashed = repository();
+        stashed.stashes = 3;
+        assert_eq!(matching(&stashed), [RepositoryFilter::All, RepositoryFilter::Stashes]);
+
+        let mut feature = repository();
+        feature.head = Some(Head::Branch("feature".to_string()));
+        assert_eq!(matching(&feature), [RepositoryFilter::All, RepositoryFilter::NonDefaultBranch]);
+    }
+
+    #[test]
+    fn detached_head_is_not_a_non_default_branch() {
+        let mut item = repository();
+        item.head = Some(Head::Detached {
+            short_sha: "abc1234".to_string(),
+            describe: None,
+        });
+        assert_eq!(matching(&item), [RepositoryFilter::All, RepositoryFilter::Detached]);
+
+        item.head = Some(Head::Unborn("dev".to_string()));
+        item.default_branch = None;
+        assert_eq!(matching(&item), [RepositoryFilter::All]);
+    }
+}
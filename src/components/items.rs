@@ -2,12 +2,47 @@ use std::fmt;
 use std::fmt::{Display, Formatter};
 use std::path::PathBuf;
 use tui::layout::Rect;
-use tui::style::{Color, Style};
+use tui::style::{Color, Modifier, Style};
 use tui::text::{Span, Spans};
 use tui::widgets::ListItem;
+use crate::git_operations::repo::{get_ahead_behind, get_default_branch, get_last_commit, get_repository_head, Head, LastCommit};
+use crate::git_operations::stash::{get_stash_count, StashEntry};
+use crate::git_operations::state::{get_operation_state, OperationState};
+use crate::git_operations::status::{get_repository_status, FileStatus, StatusCounts, StatusSection};
+use crate::git_operations::submodule::SubmoduleState;
+use chrono::Utc;
+use git2::Repository;
+
+/// Author names longer than this are cut on the repository rows
+const AUTHOR_WIDTH: usize = 12;
+
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() > width {
+        format!("{}…", text.chars().take(width - 1).collect::<String>())
+    } else {
+        text.to_string()
+    }
+}
 
 pub trait ConvertableToListItem {
-    fn convert_to_list_item(&self, chunk: Option<&Rect>) -> ListItem;
+    fn convert_to_list_item(&self, chunk: Option<&Rect>) -> ListItem<'_>;
+
+    /// Hidden items are neither rendered nor selectable
+    fn is_visible(&self) -> bool {
+        true
+    }
+}
+
+/// Kind of the entries in the repositories list
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GittenRepositoryKind {
+    #[default]
+    Folder,
+    Repository,
+    Bare,
+    Worktree,
+    Submodule,
+    Group,
 }
 
 /// Repository item for complex repository object
@@ -16,8 +51,29 @@ pub struct GittenRepositoryItem {
     pub path: PathBuf,
     pub folder_name: String,
     pub is_repository: bool,
-    pub active_branch_name: String,
-    pub files_changed: usize,
+    /// Branch or commit HEAD is on, none for non repository items
+    pub head: Option<Head>,
+    pub default_branch: Option<String>,
+    /// Number of conflicted, staged, modified and untracked files
+    pub changes: StatusCounts,
+    pub kind: GittenRepositoryKind,
+    /// Group the item belongs to, for a group header its own name
+    pub group: Option<String>,
+    /// Path of the repository a nested item, like a linked worktree, belongs to
+    pub parent: Option<PathBuf>,
+    /// Number of submodules of a repository, they are listed when the repository is expanded
+    pub submodules: usize,
+    pub submodule_state: Option<SubmoduleState>,
+    /// Commits ahead and behind the upstream of the active branch, when it has one
+    pub upstream: Option<(usize, usize)>,
+    pub stashes: usize,
+    /// Merge, rebase, cherry-pick, revert or bisect left in progress
+    pub operation: Option<OperationState>,
+    pub last_commit: Option<LastCommit>,
+    /// Whether the summary of the last commit is rendered on a second line
+    pub show_summary: bool,
+    pub collapsed: bool,
+    pub visible: bool,
 }
 
 impl GittenRepositoryItem {
@@ -25,12 +81,101 @@ impl GittenRepositoryItem {
         GittenRepositoryItemBuilder::default()
     }
 
-    pub fn set_active_branch_name(&mut self, name: String) {
-        self.active_branch_name = name;
+    pub fn set_head(&mut self, head: Option<Head>) {
+        self.head = head;
+    }
+
+    pub fn set_changes(&mut self, changes: StatusCounts) {
+        self.changes = changes;
+    }
+
+    /// Reads the branch and the working tree status of the item again
+    pub fn refresh_status(&mut self, repository: &Option<Repository>) {
+        self.set_head(get_repository_head(repository));
+        self.set_changes(StatusCounts::from_files(&get_repository_status(repository)));
+        self.upstream = get_ahead_behind(repository);
+        self.stashes = get_stash_count(repository);
+        self.operation = get_operation_state(repository);
+        self.last_commit = get_last_commit(repository);
+        self.default_branch = get_default_branch(repository);
+    }
+
+    pub fn is_group(&self) -> bool {
+        self.kind == GittenRepositoryKind::Group
+    }
+
+    /// Creates the collapsible header row of a group
+    pub fn group_header(name: String) -> GittenRepositoryItem {
+        GittenRepositoryItem::builder()
+            .folder_name(name.clone())
+            .kind(GittenRepositoryKind::Group)
+            .group(Some(name))
+            .build()
+    }
+
+    pub fn is_bare(&self) -> bool {
+        self.kind == GittenRepositoryKind::Bare
+    }
+
+    pub fn is_submodule(&self) -> bool {
+        self.kind == GittenRepositoryKind::Submodule
+    }
+
+    /// Groups and repositories with submodules can be collapsed
+    pub fn is_collapsible(&self) -> bool {
+        self.is_group() || self.submodules > 0
+    }
+
+    fn indent(&self) -> usize {
+        let mut indent = 0;
+        if !self.is_group() && self.group.is_some() {
+            indent += 2;
+        }
+        if self.parent.is_some() {
+            indent += 2;
+        }
+        indent
+    }
+
+    /// Short status markers shown before the branch name
+    fn badges(&self) -> Vec<String> {
+        let mut badges = Vec::new();
+        if let Some(operation) = self.operation {
+            badges.push(operation.to_string());
+        }
+        if let Some((ahead, behind)) = self.upstream {
+            if ahead > 0 || behind > 0 {
+                badges.push(format!("↑{} ↓{}", ahead, behind));
+            }
+        }
+        if self.stashes > 0 {
+            badges.push(format!("≡{}", self.stashes));
+        }
+        badges
     }
 
-    pub fn set_files_changed(&mut self, files_changed: usize) {
-        self.files_changed = files_changed;
+    fn label(&self) -> String {
+        match self.kind {
+            GittenRepositoryKind::Worktree if self.parent.is_some() => format!("↳ {}", self.folder_name),
+            GittenRepositoryKind::Worktree => format!("{} [worktree]", self.folder_name),
+            GittenRepositoryKind::Bare => format!("{} [bare]", self.folder_name),
+            GittenRepositoryKind::Submodule => format!(
+                "↳ {} {}",
+                self.folder_name,
+                match self.submodule_state {
+                    Some(SubmoduleState::Matching) => "✓",
+                    Some(SubmoduleState::Modified) => "✗ modified",
+                    _ => "(not initialized)",
+                }
+            ),
+            _ if self.submodules > 0 => format!(
+                "{} {} [{} submodules]",
+                if self.collapsed { "▸" } else { "▾" },
+                self.folder_name,
+                self.submodules
+            ),
+            _ => self.folder_name.clone(),
+        }
     }
 }
 
@@ -41,42 +186,70 @@ impl Display for GittenRepositoryItem {
 }
 
 impl ConvertableToListItem for GittenRepositoryItem {
-    fn convert_to_list_item(&self, chunk: Option<&Rect>) -> ListItem {
+    fn convert_to_list_item(&self, chunk: Option<&Rect>) -> ListItem<'_> {
         let mut lines: Spans = Spans::default();
         let mut line_color = Color::Reset;
+        if self.is_group() {
+            lines.0.push(Span::styled(
+                format!("{} {}", if self.collapsed { "▸" } else { "▾" }, self.folder_name),
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+            ));
+            return ListItem::new(lines);
+        }
+
+        let label = self.label();
+        lines.0.push(Span::raw(" ".repeat(self.indent())));
         if self.is_repository {
-            let mut margin = 4;
-            if self.files_changed > 0 {
-                margin += 1;
-            }
-            let repeat_time = if chunk.unwrap().width
-                > ((self.active_branch_name.len() as u16)
-                + (self.folder_name.len() as u16)
-                + margin)
-            {
-                chunk.unwrap().width
-                    - ((self.active_branch_name.len() as u16)
-                    + (self.folder_name.len() as u16)
-                    + margin)
+            let badges: String = self.badges().iter().map(|b| format!("{} ", b)).collect();
+            let commit = self
+                .last_commit
+                .as_ref()
+                .map(|c| format!("{} {}  ", c.age(Utc::now().timestamp()), truncate(&c.author, AUTHOR_WIDTH)))
+                .unwrap_or_default();
+            let (head, head_style) = match &self.head {
+                Some(head @ Head::Detached { .. }) => {
+                    (head.to_string(), Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+                }
+                Some(head @ Head::Unborn(_)) => (head.to_string(), Style::default().add_modifier(Modifier::ITALIC)),
+                Some(head) => (head.to_string(), Style::default()),
+                None => (String::new(), Style::default()),
+            };
+            let changes = if self.changes.is_dirty() {
+                format!(" {})", self.changes)
             } else {
-                0
+                String::from(")")
             };
-            lines.0.push(Span::from(self.folder_name.clone()));
-            lines.0.push(Span::from(" ".repeat((repeat_time) as usize)));
-            lines.0.push(Span::raw("("));
-            lines
-                .0
-                .push(Span::from(self.active_branch_name.to_string()));
-            lines
-                .0
-                .push(Span::from(if self.files_changed > 0 { "*" } else { "" }));
-            lines.0.push(Span::raw(")"));
-            line_color = Color::Green
+
+            // Borders of the block take two columns, the opening parenthesis one more
+            let status_width =
+                commit.chars().count() + badges.chars().count() + head.chars().count() + changes.chars().count() + 1;
+            let used_width = self.indent() + label.chars().count() + status_width + 2;
+            let repeat_time = (chunk.unwrap().width as usize).saturating_sub(used_width);
+
+            lines.0.push(Span::from(label));
+            lines.0.push(Span::from(" ".repeat(repeat_time.max(1))));
+            lines.0.push(Span::styled(commit, Style::default().add_modifier(Modifier::DIM)));
+            lines.0.push(Span::from(format!("{}(", badges)));
+            lines.0.push(Span::styled(head, head_style));
+            lines.0.push(Span::from(changes));
+            line_color = Color::Green;
+
+            if let Some(c) = self.last_commit.as_ref().filter(|_| self.show_summary) {
+                let summary = Spans::from(vec![
+                    Span::raw(" ".repeat(self.indent() + 2)),
+                    Span::styled(c.summary.clone(), Style::default().add_modifier(Modifier::ITALIC)),
+                ]);
+                return ListItem::new(vec![lines, summary]).style(Style::default().fg(Color::White).bg(line_color));
+            }
         } else {
-            lines.0.push(Span::from(self.folder_name.clone()));
+            lines.0.push(Span::from(label));
         }
         ListItem::new(lines).style(Style::default().fg(Color::White).bg(line_color))
     }
+
+    fn is_visible(&self) -> bool {
+        self.visible
+    }
 }
 
 #[derive(Default)]
@@ -84,8 +257,9 @@ pub struct GittenRepositoryItemBuilder {
     pub path: PathBuf,
     pub folder_name: String,
     pub is_repository: bool,
-    pub active_branch_name: String,
-    pub files_changed: usize,
+    pub kind: GittenRepositoryKind,
+    pub group: Option<String>,
+    pub show_summary: bool,
 }
 
 impl GittenRepositoryItemBuilder {
@@ -104,13 +278,18 @@ impl GittenRepositoryItemBuilder {
         self
     }
 
-    pub fn active_branch_name(mut self, active_branch_name: String) -> GittenRepositoryItemBuilder {
-        self.active_branch_name = active_branch_name;
+    pub fn kind(mut self, kind: GittenRepositoryKind) -> GittenRepositoryItemBuilder {
+        self.kind = kind;
+        self
+    }
+
+    pub fn group(mut self, group: Option<String>) -> GittenRepositoryItemBuilder {
+        self.group = group;
         self
     }
 
-    pub fn files_changed(mut self, files_changed: usize) -> GittenRepositoryItemBuilder {
-        self.files_changed = files_changed;
+    pub fn show_summary(mut self, show_summary: bool) -> GittenRepositoryItemBuilder {
+        self.show_summary = show_summary;
         self
     }
 
@@ -119,8 +298,21 @@ impl GittenRepositoryItemBuilder {
             path: self.path,
             folder_name: self.folder_name,
             is_repository: self.is_repository,
-            active_branch_name: self.active_branch_name,
-            files_changed: self.files_changed
+            head: None,
+            default_branch: None,
+            changes: StatusCounts::default(),
+            kind: self.kind,
+            group: self.group,
+            parent: None,
+            submodules: 0,
+            submodule_state: None,
+            upstream: None,
+            stashes: 0,
+            operation: None,
+            last_commit: None,
+            show_summary: self.show_summary,
+            collapsed: false,
+            visible: true,
         }
     }
 
@@ -130,7 +322,35 @@ impl GittenRepositoryItemBuilder {
 pub type GittenStringItem = String;
 
 impl ConvertableToListItem for GittenStringItem {
-    fn convert_to_list_item(&self, _chunk: Option<&Rect>) -> ListItem {
+    fn convert_to_list_item(&self, _chunk: Option<&Rect>) -> ListItem<'_> {
+        ListItem::new(vec![Spans::from(vec![Span::raw(self.to_string())])])
+    }
+}
+
+/// File item for the working tree status
+pub type GittenStatusItem = FileStatus;
+
+impl ConvertableToListItem for GittenStatusItem {
+    fn convert_to_list_item(&self, _chunk: Option<&Rect>) -> ListItem<'_> {
+        let color = match self.section {
+            StatusSection::Conflicted => Color::Magenta,
+            StatusSection::Staged => Color::Green,
+            StatusSection::Unstaged => Color::Red,
+            StatusSection::Untracked => Color::Gray,
+        };
+        ListItem::new(vec![Spans::from(vec![
+            Span::styled(format!("{:<10} ", self.section.to_string()), Style::default().fg(color)),
+            Span::raw(format!("{:<13} ", self.change.to_string())),
+            Span::raw(self.to_string()),
+        ])])
+    }
+}
+
+/// Stash item for the stash list
+pub type GittenStashItem = StashEntry;
+
+impl ConvertableToListItem for GittenStashItem {
+    fn convert_to_list_item(&self, _chunk: Option<&Rect>) -> ListItem<'_> {
         ListItem::new(vec![Spans::from(vec![Span::raw(self.to_string())])])
     }
-}
\ No newline at end of file
+}
@@ -1,6 +1,7 @@
 #[derive(Clone)]
 pub struct Logs {
     pub logs: String,
+    pub title: Option<String>,
     pub offset: (u16, u16)
 }
 
@@ -22,7 +23,8 @@ impl Logs {
 
 #[derive(Default)]
 pub struct LogsBuilder {
-    pub logs: String
+    pub logs: String,
+    pub title: Option<String>
 }
 
 impl LogsBuilder {
@@ -31,9 +33,15 @@ impl LogsBuilder {
         self
     }
 
+    pub fn title(mut self, title: String) -> LogsBuilder {
+        self.title = Some(title);
+        self
+    }
+
     pub fn build(self) -> Logs {
         Logs {
             logs: self.logs,
+            title: self.title,
             offset: (0, 0)
         }
     }
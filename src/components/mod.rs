@@ -1,7 +1,11 @@
 pub mod application;
+pub mod confirm;
+pub mod diff;
+pub mod filter;
 pub mod selection;
 pub mod items;
 pub mod modes;
 pub mod logs;
+pub mod workspaces;
 
 mod stateful_list;
@@ -4,5 +4,7 @@ pub enum InputMode {
     Editing,
     Search,
     Command,
-    Logs
+    Logs,
+    Diff,
+    Confirm
 }
\ No newline at end of file
@@ -1,11 +1,15 @@
+use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::fmt::{Display, Formatter};
 
-#[derive(PartialEq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum Selection {
+    #[default]
     Repositories,
     Tags,
     Branches,
+    Status,
+    Stash,
 }
 
 impl Display for Selection {
@@ -17,6 +21,8 @@ impl Display for Selection {
                 Selection::Repositories => "(R)epositories",
                 Selection::Tags => "(T)ags",
                 Selection::Branches => "(B)ranches",
+                Selection::Status => "(S)tatus",
+                Selection::Stash => "Stas(h)",
             }
         )
     }
@@ -1,63 +1,78 @@
 use std::fmt::Display;
 use tui::widgets::ListState;
+use crate::components::items::ConvertableToListItem;
 
 #[derive(Default)]
 pub struct StatefulList<T> {
     pub state: ListState,
     pub items: Vec<T>,
+    /// State handed to the renderer, selection is the position among the visible items
+    view_state: ListState,
 }
 
-impl<T: Display + Default> StatefulList<T> {
+impl<T: Display + Default + ConvertableToListItem> StatefulList<T> {
     pub fn builder() -> StatefulListBuilder<T> {
         StatefulListBuilder::default()
     }
 
     pub fn next(&mut self) {
-        let i = match self.state.selected() {
-            Some(i) => {
-                if !self.items.is_empty() {
-                    if i >= self.items.len() - 1 {
-                        0
-                    } else {
-                        i + 1
-                    }
-                } else {
-                    0
-                }
-            }
+        let start = match self.state.selected() {
+            Some(i) => i + 1,
             None => 0,
         };
-        self.state.select(Some(i));
+        let len = self.items.len();
+        self.select_visible((0..len).map(|o| (start + o) % len));
     }
 
     pub fn previous(&mut self) {
-        let i = match self.state.selected() {
-            Some(i) => {
-                if !self.items.is_empty() {
-                    if i == 0 {
-                        self.items.len() - 1
-                    } else {
-                        i - 1
-                    }
-                } else {
-                    0
-                }
-            }
-            None => 0,
+        let len = self.items.len();
+        let start = match self.state.selected() {
+            Some(i) => i + len,
+            None => len,
         };
-        self.state.select(Some(i));
+        self.select_visible((1..=len).map(|o| (start - o) % len));
+    }
+
+    fn select_visible(&mut self, mut candidates: impl Iterator<Item = usize>) {
+        if let Some(i) = candidates.find(|&i| self.items[i].is_visible()) {
+            self.state.select(Some(i));
+        }
     }
 
     pub fn unselect(&mut self) {
         self.state.select(None);
     }
 
+    /// Moves the selection to a visible item when the selected one got hidden
+    pub fn ensure_visible_selection(&mut self) {
+        if let Some(i) = self.state.selected() {
+            if i >= self.items.len() {
+                self.state.select(None);
+            } else if !self.items[i].is_visible() {
+                self.select_visible((0..=i).rev().chain(i + 1..self.items.len()));
+                if self.state.selected() == Some(i) {
+                    self.state.select(None);
+                }
+            }
+        }
+    }
+
+    /// Returns the items together with the state to render the visible items with
+    pub fn view(&mut self) -> (&[T], &mut ListState) {
+        let selected = self.state.selected().map(|s| {
+            self.items[..s].iter().filter(|i| i.is_visible()).count()
+        });
+        self.view_state.select(selected);
+        (&self.items, &mut self.view_state)
+    }
+
     pub fn search(&mut self, input: &str) {
         self.items.iter().enumerate().for_each(|(i, _x)| {
-            if self.items[i]
-                .to_string()
-                .to_lowercase()
-                .contains(&input.to_lowercase())
+            if self.items[i].is_visible()
+                && self.items[i]
+                    .to_string()
+                    .to_lowercase()
+                    .contains(&input.to_lowercase())
             {
                 self.state.select(Some(i));
             }
@@ -70,7 +85,7 @@ pub struct StatefulListBuilder<T> {
     pub items: Vec<T>
 }
 
-impl<T: Display + Default> StatefulListBuilder<T> {
+impl<T: Display + Default + ConvertableToListItem> StatefulListBuilder<T> {
     pub fn items(mut self, items: Vec<T>) -> StatefulListBuilder<T> {
         self.items = items;
         self
@@ -79,7 +94,8 @@ impl<T: Display + Default> StatefulListBuilder<T> {
     pub fn build(self) -> StatefulList<T> {
         StatefulList {
             state: ListState::default(),
-            items: self.items
+            items: self.items,
+            view_state: ListState::default(),
         }
     }
-}
\ No newline at end of file
+}
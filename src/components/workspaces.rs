@@ -0,0 +1,58 @@
+use crate::components::application::App;
+use std::path::Path;
+
+/// Workspaces opened in gitten, each one is shown as a tab
+pub struct Workspaces {
+    pub apps: Vec<App>,
+    pub index: usize,
+}
+
+impl Workspaces {
+    pub fn builder() -> WorkspacesBuilder {
+        WorkspacesBuilder::default()
+    }
+
+    pub fn active(&mut self) -> &mut App {
+        &mut self.apps[self.index]
+    }
+
+    pub fn next(&mut self) {
+        self.index = (self.index + 1) % self.apps.len();
+    }
+
+    pub fn previous(&mut self) {
+        self.index = (self.index + self.apps.len() - 1) % self.apps.len();
+    }
+
+    /// Folder names of the workspaces to be used as tab titles
+    pub fn titles(&self) -> Vec<String> {
+        self.apps
+            .iter()
+            .map(|a| {
+                Path::new(&a.path)
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_else(|| a.path.clone())
+            })
+            .collect()
+    }
+}
+
+#[derive(Default)]
+pub struct WorkspacesBuilder {
+    pub apps: Vec<App>,
+}
+
+impl WorkspacesBuilder {
+    pub fn app(mut self, app: App) -> WorkspacesBuilder {
+        self.apps.push(app);
+        self
+    }
+
+    pub fn build(self) -> Workspaces {
+        Workspaces {
+            apps: self.apps,
+            index: 0,
+        }
+    }
+}
@@ -0,0 +1,252 @@
+use glob::Pattern;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use serde::Deserialize;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the workspace configuration file looked up in the root folder
+pub const CONFIG_FILE_NAME: &str = ".gitten.toml";
+
+/// Name of the file in the root folder listing ignored entries in gitignore syntax
+pub const IGNORE_FILE_NAME: &str = ".gittenignore";
+
+const DEFAULT_TICK_RATE: u64 = 5000;
+
+/// Order of the repositories within their group
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RepositorySort {
+    #[default]
+    Name,
+    /// Most recent HEAD commit first
+    Recent,
+}
+
+impl Display for RepositorySort {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            RepositorySort::Name => write!(f, "by name"),
+            RepositorySort::Recent => write!(f, "by recent commit"),
+        }
+    }
+}
+
+/// Workspace configuration read from `.gitten.toml` in the root folder,
+/// or from `gitten/config.toml` under the user config directory.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct WorkspaceConfig {
+    /// Extra repository paths, absolute or relative to the root folder
+    pub repositories: Vec<String>,
+    /// Glob patterns matched against the relative path and the name of each entry
+    pub ignore: Vec<String>,
+    /// How many folder levels are searched for repositories
+    pub depth: Option<usize>,
+    /// Remote used when a remote command is given without a remote name
+    pub default_remote: Option<String>,
+    /// Tick rate of the application in milliseconds
+    pub tick_rate: Option<u64>,
+    /// Whether entries starting with a dot are listed
+    pub show_hidden: bool,
+    /// Named groups of repositories, each with glob patterns matched against the relative path
+    pub groups: BTreeMap<String, Vec<String>>,
+    /// Whether non repository items are hidden at start
+    pub hide_non_repositories: bool,
+    /// Order of the repositories at start
+    pub sort: RepositorySort,
+    /// Whether the summary of the last commit is shown under each repository
+    pub commit_summary: bool,
+    /// Rules of the `.gittenignore` file of the root folder
+    #[serde(skip)]
+    pub gittenignore: Option<Gitignore>,
+}
+
+impl WorkspaceConfig {
+    /// Loads the workspace configuration for the given root folder. The root folder's
+    /// `.gitten.toml` takes precedence over the user level configuration, and defaults
+    /// are used when neither exists.
+    pub fn load(root: &Path) -> Result<WorkspaceConfig, String> {
+        let user_config = dirs::config_dir().map(|d| d.join("gitten").join("config.toml"));
+
+        let config_path = [Some(root.join(CONFIG_FILE_NAME)), user_config]
+            .into_iter()
+            .flatten()
+            .find(|p| p.is_file());
+
+        let mut config = match config_path {
+            Some(p) => WorkspaceConfig::from_file(&p)?,
+            None => WorkspaceConfig::default(),
+        };
+        config.gittenignore = WorkspaceConfig::load_gittenignore(root)?;
+        Ok(config)
+    }
+
+    fn load_gittenignore(root: &Path) -> Result<Option<Gitignore>, String> {
+        let path = root.join(IGNORE_FILE_NAME);
+        if !path.is_file() {
+            return Ok(None);
+        }
+
+        let mut builder = GitignoreBuilder::new(root);
+        if let Some(e) = builder.add(&path) {
+            return Err(format!("{}: {}", path.display(), e));
+        }
+        builder
+            .build()
+            .map(Some)
+            .map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    fn from_file(path: &Path) -> Result<WorkspaceConfig, String> {
+        let content = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let config: WorkspaceConfig =
+            toml::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        let patterns = config.ignore.iter().chain(config.groups.values().flatten());
+        if let Some(p) = patterns.into_iter().find(|p| Pattern::new(p).is_err()) {
+            return Err(format!("{}: invalid pattern '{}'", path.display(), p));
+        }
+        Ok(config)
+    }
+
+    pub fn tick_rate(&self) -> u64 {
+        self.tick_rate.unwrap_or(DEFAULT_TICK_RATE)
+    }
+
+    /// Resolves the extra repository paths against the root folder
+    pub fn repository_paths(&self, root: &Path) -> Vec<PathBuf> {
+        self.repositories
+            .iter()
+            .map(|r| match r.strip_prefix("~/") {
+                Some(r) => dirs::home_dir().unwrap_or_default().join(r),
+                None => root.join(r),
+            })
+            .collect()
+    }
+
+    /// Checks whether an entry is ignored by name or by its path relative to the root folder,
+    /// either by the ignore patterns or by the `.gittenignore` file
+    pub fn is_ignored(&self, relative_path: &Path, is_dir: bool) -> bool {
+        let name = relative_path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        if !self.show_hidden && name.starts_with('.') {
+            return true;
+        }
+
+        if let Some(gittenignore) = &self.gittenignore {
+            if gittenignore.matched(relative_path, is_dir).is_ignore() {
+                return true;
+            }
+        }
+
+        self.ignore.iter().filter_map(|p| Pattern::new(p).ok()).any(|p| {
+            p.matches(&name) || p.matches_path(relative_path)
+        })
+    }
+
+    /// Finds the group of an entry. Configured groups come first, otherwise entries
+    /// below the root are grouped by their parent folder.
+    pub fn group_for(&self, relative_path: &Path) -> Option<String> {
+        let configured = self.groups.iter().find(|(_, patterns)| {
+            patterns
+                .iter()
+                .filter_map(|p| Pattern::new(p).ok())
+                .any(|p| p.matches_path(relative_path))
+        });
+
+        match configured {
+            Some((name, _)) => Some(name.clone()),
+            None if relative_path.is_relative() => relative_path
+                .parent()
+                .filter(|p| !p.as_os_str().is_empty())
+                .map(|p| p.to_string_lossy().to_string()),
+            None => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn workspace(config: &str, gittenignore: Option<&str>) -> (TempDir, WorkspaceConfig) {
+        let root = TempDir::new().unwrap();
+        fs::write(root.path().join(CONFIG_FILE_NAME), config).unwrap();
+        if let Some(rules) = gittenignore {
+            fs::write(root.path().join(IGNORE_FILE_NAME), rules).unwrap();
+        }
+        let config = WorkspaceConfig::load(root.path()).unwrap();
+        (root, config)
+    }
+
+    #[test]
+    fn entries_are_ignored_by_name_or_relative_path() {
+        let (_root, config) = workspace("ignore = [\"target\", \"vendor/*\"]", None);
+
+        assert!(config.is_ignored(Path::new("target"), true));
+        assert!(config.is_ignored(Path::new("app/target"), true));
+        assert!(config.is_ignored(Path::new("vendor/lib"), true));
+        assert!(!config.is_ignored(Path::new("app/vendor"), true));
+        assert!(!config.is_ignored(Path::new("app"), true));
+    }
+
+    #[test]
+    fn hidden_entries_are_ignored_unless_shown() {
+        let (_root, hidden) = workspace("", None);
+        let (_root, shown) = workspace("show-hidden = true", None);
+
+        assert!(hidden.is_ignored(Path::new(".cache"), true));
+        assert!(hidden.is_ignored(Path::new("app/.cache"), true));
+        assert!(!shown.is_ignored(Path::new(".cache"), true));
+    }
+
+    #[test]
+    fn entries_are_ignored_by_the_gittenignore_file() {
+        let (_root, config) = workspace("", Some("archive/\n*.bak\n!keep.bak\n"));
+
+        assert!(config.is_ignored(Path::new("archive"), true));
+        assert!(!config.is_ignored(Path::new("archive"), false));
+        assert!(config.is_ignored(Path::new("app/old.bak"), true));
+        assert!(!config.is_ignored(Path::new("keep.bak"), true));
+        assert!(!config.is_ignored(Path::new("app"), true));
+    }
+
+    #[test]
+    fn invalid_ignore_pattern_is_rejected() {
+        let root = TempDir::new().unwrap();
+        fs::write(root.path().join(CONFIG_FILE_NAME), "ignore = [\"[\"]").unwrap();
+
+        let error = WorkspaceConfig::load(root.path()).unwrap_err();
+        assert!(error.ends_with("invalid pattern '['"), "{}", error);
+    }
+
+    #[test]
+    fn configured_groups_come_before_parent_folders() {
+        let (_root, config) = workspace(
+            "[groups]\nbackend = [\"services/api*\", \"db\"]\nfrontend = [\"web/*\"]",
+            None,
+        );
+
+        assert_eq!(config.group_for(Path::new("services/api-v2")).as_deref(), Some("backend"));
+        assert_eq!(config.group_for(Path::new("db")).as_deref(), Some("backend"));
+        assert_eq!(config.group_for(Path::new("web/shop")).as_deref(), Some("frontend"));
+        assert_eq!(config.group_for(Path::new("services/auth")).as_deref(), Some("services"));
+        assert_eq!(config.group_for(Path::new("tools/ci/runner")).as_deref(), Some("tools/ci"));
+        assert_eq!(config.group_for(Path::new("loose")), None);
+    }
+
+    #[test]
+    fn absolute_paths_only_join_configured_groups() {
+        let (_root, config) = workspace("[groups]\nextra = [\"/opt/repos/*\"]", None);
+
+        assert_eq!(config.group_for(Path::new("/opt/repos/lib")).as_deref(), Some("extra"));
+        assert_eq!(config.group_for(Path::new("/srv/other/lib")), None);
+    }
+}
@@ -0,0 +1,68 @@
+use crate::git_operations::repo::git_credentials_callback;
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use git2::build::RepoBuilder;
+use git2::{FetchOptions, RemoteCallbacks};
+use std::path::{Path, PathBuf};
+use std::thread;
+
+pub enum CloneEvent {
+    Progress { received: usize, total: usize },
+    Done(Result<PathBuf, String>),
+}
+
+/// Clone running in the background, its events are read from the receiver
+pub struct CloneJob {
+    pub name: String,
+    pub receiver: Receiver<CloneEvent>,
+    pub progress: (usize, usize),
+}
+
+/// Folder name a url is cloned into when no folder is given
+pub fn folder_name_from_url(url: &str) -> String {
+    let name = url
+        .trim_end_matches('/')
+        .rsplit(['/', ':'])
+        .next()
+        .unwrap_or(url);
+    name.strip_suffix(".git").unwrap_or(name).to_string()
+}
+
+/// Starts cloning `url` into `path` on a separate thread
+pub fn clone_repository(url: &str, path: &Path) -> CloneJob {
+    let (tx, rx): (Sender<CloneEvent>, Receiver<CloneEvent>) = unbounded();
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let url = url.to_string();
+    let path = path.to_path_buf();
+
+    thread::spawn(move || {
+        let progress_tx = tx.clone();
+        let mut cb = RemoteCallbacks::new();
+        cb.credentials(git_credentials_callback);
+        cb.transfer_progress(move |stats| {
+            let _ = progress_tx.send(CloneEvent::Progress {
+                received: stats.received_objects(),
+                total: stats.total_objects(),
+            });
+            true
+        });
+
+        let mut fo = FetchOptions::new();
+        fo.remote_callbacks(cb);
+
+        let result = RepoBuilder::new()
+            .fetch_options(fo)
+            .clone(&url, &path)
+            .map(|_| path)
+            .map_err(|e| e.message().to_string());
+        let _ = tx.send(CloneEvent::Done(result));
+    });
+
+    CloneJob {
+        name,
+        receiver: rx,
+        progress: (0, 0),
+    }
+}
@@ -0,0 +1,289 @@
+use crate::git_operations::hooks::{has_hook, run_hook, run_post_hook};
+use crate::git_operations::sign::{should_sign, sign_buffer};
+use crate::git_operations::state::OperationState;
+use crate::git_operations::status::{get_repository_status, StatusSection};
+use git2::{Oid, Repository, RepositoryState, Signature, Tree};
+use std::fs;
+use std::path::PathBuf;
+
+/// Name of the file in the git folder the commit message is edited in
+const COMMIT_MESSAGE_FILE: &str = "COMMIT_EDITMSG";
+
+/// Commits the index on HEAD with the signature from the git config of the repository
+pub fn commit(repository: &Repository, message: &str) -> Result<String, git2::Error> {
+    ensure_no_operation(repository)?;
+    let tree = prepare_tree(repository)?;
+    // An unborn branch gets its first commit without a parent
+    let parent = repository.head().and_then(|h| h.peel_to_commit()).ok();
+    if parent.as_ref().is_some_and(|p| p.tree_id() == tree.id()) {
+        return Err(git2::Error::from_str("Nothing to commit, stage some changes first"));
+    }
+
+    let message = run_commit_msg_hook(repository, message)?;
+    let sig = repository.signature()?;
+    let parents: Vec<&git2::Commit> = parent.iter().collect();
+    let oid = create_commit(repository, &sig, &sig, &message, &tree, &parents)?;
+    Ok(format!("Committed {:.7}!{}", oid, run_post_hook(repository, "post-commit", &[])))
+}
+
+/// Replaces the HEAD commit with the index, keeping its message when no new one is given
+pub fn amend(repository: &Repository, message: Option<&str>) -> Result<String, git2::Error> {
+    ensure_no_operation(repository)?;
+    let head = repository.head()?.peel_to_commit()?;
+    let tree = prepare_tree(repository)?;
+    let message = run_commit_msg_hook(repository, message.unwrap_or_else(|| head.message().unwrap_or_default()))?;
+    let sig = repository.signature()?;
+    let parents: Vec<git2::Commit> = head.parents().collect();
+    let parents: Vec<&git2::Commit> = parents.iter().collect();
+    let oid = write_commit(repository, &head.author(), &sig, &message, &tree, &parents)?;
+    let reflog = format!("commit (amend): {}", message.lines().next().unwrap_or_default());
+    update_head(repository, oid, Some(head.id()), &reflog)?;
+    Ok(format!("Amended {:.7}!{}", oid, run_post_hook(repository, "post-commit", &[])))
+}
+
+/// The commit ending a merge, cherry-pick, revert or rebase is made by `:continue`, which gives it
+/// the parents of the operation and clears its state. Commits are fine while bisecting.
+fn ensure_no_operation(repository: &Repository) -> Result<(), git2::Error> {
+    match repository.state() {
+        RepositoryState::Clean | RepositoryState::Bisect => Ok(()),
+        state => Err(git2::Error::from_str(&format!(
+            "{} is in progress, use :continue to commit it or :abort",
+            OperationState { state, progress: None }
+        ))),
+    }
+}
+
+/// Creates a commit and moves HEAD, or the branch it points to, to it. The commit is signed when
+/// `commit.gpgsign` is set.
+pub fn create_commit(
+    repository: &Repository,
+    author: &Signature,
+    committer: &Signature,
+    message: &str,
+    tree: &Tree,
+    parents: &[&git2::Commit],
+) -> Result<Oid, git2::Error> {
+    let oid = write_commit(repository, author, committer, message, tree, parents)?;
+    let kind = match parents.len() {
+        0 => "commit (initial)",
+        1 => "commit",
+        _ => "commit (merge)",
+    };
+    let reflog = format!("{}: {}", kind, message.lines().next().unwrap_or_default());
+    update_head(repository, oid, parents.first().map(|p| p.id()), &reflog)?;
+    Ok(oid)
+}
+
+fn write_commit(
+    repository: &Repository,
+    author: &Signature,
+    committer: &Signature,
+    message: &str,
+    tree: &Tree,
+    parents: &[&git2::Commit],
+) -> Result<Oid, git2::Error> {
+    if !should_sign(repository, "commit.gpgsign") {
+        return repository.commit(None, author, committer, message, tree, parents);
+    }
+    let buffer = repository.commit_create_buffer(author, committer, message, tree, parents)?;
+    let buffer = buffer
+        .as_str()
+        .ok_or_else(|| git2::Error::from_str("Commit is not valid UTF-8"))?;
+    let signature = sign_buffer(repository, committer, buffer)?;
+    repository.commit_signed(buffer, &signature, None)
+}
+
+/// Moves HEAD, or the branch it points to, from `expected` to a new commit and fails when it was
+/// moved in the meantime. HEAD is moved here rather than by libgit2, which only moves it to a
+/// child of the current tip.
+fn update_head(repository: &Repository, oid: Oid, expected: Option<Oid>, reflog: &str) -> Result<(), git2::Error> {
+    let head = repository.find_reference("HEAD")?;
+    let name = head.symbolic_target().unwrap_or("HEAD");
+    match expected {
+        Some(current) => repository.reference_matching(name, oid, true, current, reflog)?,
+        // An unborn branch gets its first commit, it must still not exist
+        None => repository.reference(name, oid, false, reflog)?,
+    };
+    Ok(())
+}
+
+/// Runs the pre-commit hook, then writes the tree of the index to commit. The index is read again
+/// in between, so the files the hook stages (formatters, linters) are committed like with git.
+pub fn prepare_tree(repository: &Repository) -> Result<Tree<'_>, git2::Error> {
+    run_hook(repository, "pre-commit", &[], None)?;
+    let mut index = repository.index()?;
+    index.read(true)?;
+    repository.find_tree(index.write_tree()?)
+}
+
+/// Runs the commit-msg hook on the message written to the commit message file, like git does
+pub fn run_commit_msg_hook(repository: &Repository, message: &str) -> Result<String, git2::Error> {
+    if !has_hook(repository, "commit-msg") {
+        return Ok(message.to_string());
+    }
+    let path = repository.path().join(COMMIT_MESSAGE_FILE);
+    fs::write(&path, git2::message_prettify(message, None)?).map_err(|e| git2::Error::from_str(&e.to_string()))?;
+    run_hook(repository, "commit-msg", &[&path.to_string_lossy()], None)?;
+    read_commit_message(&path)?.ok_or_else(|| git2::Error::from_str("Aborting commit due to empty commit message"))
+}
+
+/// Writes the commit message template listing the staged files, like git does before opening
+/// the editor, and returns its path
+pub fn write_commit_template(repository: &Option<Repository>) -> Result<PathBuf, git2::Error> {
+    let r = repository
+        .as_ref()
+        .ok_or_else(|| git2::Error::from_str("Not a repository"))?;
+    // Refused before the editor opens rather than once the message is written
+    ensure_no_operation(r)?;
+    let staged: Vec<String> = get_repository_status(repository)
+        .into_iter()
+        .filter(|f| f.section == StatusSection::Staged)
+        .map(|f| format!("#\t{}:   {}", f.change, f))
+        .collect();
+    if staged.is_empty() {
+        return Err(git2::Error::from_str("Nothing to commit, stage some changes first"));
+    }
+
+    let template = format!(
+        "\n# Please enter the commit message for your changes. Lines starting\n\
+         # with '#' will be ignored, and an empty message aborts the commit.\n\
+         #\n# Changes to be committed:\n{}\n",
+        staged.join("\n")
+    );
+    let path = r.path().join(COMMIT_MESSAGE_FILE);
+    fs::write(&path, template).map_err(|e| git2::Error::from_str(&e.to_string()))?;
+    Ok(path)
+}
+
+/// Reads the edited commit message without its comments, none when it is empty
+pub fn read_commit_message(path: &PathBuf) -> Result<Option<String>, git2::Error> {
+    let content = fs::read_to_string(path).map_err(|e| git2::Error::from_str(&e.to_string()))?;
+    let message = git2::message_prettify(content, Some(b'#'))?;
+    Ok(Some(message).filter(|m| !m.trim().is_empty()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git_operations::test_utils::{commit_all, init_repository, write_file};
+
+    fn stage(repository: &Repository, path: &str, content: &str) {
+        write_file(repository, path, content);
+        let mut index = repository.index().unwrap();
+        index.add_path(std::path::Path::new(path)).unwrap();
+        index.write().unwrap();
+    }
+
+    fn last_reflog(repository: &Repository) -> String {
+        let reflog = repository.reflog("HEAD").unwrap();
+        reflog.get(0).unwrap().message().unwrap().to_string()
+    }
+
+    #[test]
+    fn commits_are_logged_by_kind() {
+        let (_dir, repository) = init_repository();
+        stage(&repository, "a.txt", "a\n");
+        commit(&repository, "first").unwrap();
+        assert_eq!(last_reflog(&repository), "commit (initial): first");
+
+        stage(&repository, "a.txt", "b\n");
+        commit(&repository, "second").unwrap();
+        assert_eq!(last_reflog(&repository), "commit: second");
+
+        let parent = repository.head().unwrap().peel_to_commit().unwrap().parent_id(0).unwrap();
+        let branch = repository.head().unwrap().shorthand().map(|b| b.to_string());
+        amend(&repository, Some("second, amended")).unwrap();
+        assert_eq!(last_reflog(&repository), "commit (amend): second, amended");
+        let head = repository.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(head.parent_ids().collect::<Vec<Oid>>(), vec![parent]);
+        assert_eq!(repository.head().unwrap().shorthand().map(|b| b.to_string()), branch);
+    }
+
+    #[test]
+    fn commit_is_refused_during_a_merge() {
+        let (_dir, repository) = init_repository();
+        write_file(&repository, "a.txt", "a\n");
+        commit_all(&repository, "init");
+        let head = repository.head().unwrap().target().unwrap();
+        std::fs::write(repository.path().join("MERGE_HEAD"), format!("{}\n", head)).unwrap();
+        stage(&repository, "a.txt", "b\n");
+
+        let error = commit(&repository, "single parent").unwrap_err();
+        assert_eq!(error.message(), "MERGING is in progress, use :continue to commit it or :abort");
+        assert!(amend(&repository, None).is_err());
+        assert_eq!(repository.state(), RepositoryState::Merge);
+        assert_eq!(repository.head().unwrap().target(), Some(head));
+    }
+
+    #[test]
+    fn detached_head_is_moved() {
+        let (_dir, repository) = init_repository();
+        write_file(&repository, "a.txt", "a\n");
+        commit_all(&repository, "init");
+        let init = repository.head().unwrap().target().unwrap();
+        repository.set_head_detached(init).unwrap();
+
+        stage(&repository, "a.txt", "b\n");
+        commit(&repository, "detached").unwrap();
+        let head = repository.head().unwrap();
+        assert!(!head.is_branch());
+        assert_eq!(head.peel_to_commit().unwrap().parent_id(0).unwrap(), init);
+        assert!(repository.find_reference("HEAD").unwrap().symbolic_target().is_none());
+        let branches = repository.branches(None).unwrap();
+        assert!(branches.flatten().all(|(b, _)| b.get().target() == Some(init)));
+    }
+
+    #[test]
+    fn moved_branch_is_not_overwritten() {
+        let (_dir, repository) = init_repository();
+        write_file(&repository, "a.txt", "a\n");
+        commit_all(&repository, "init");
+        let init = repository.head().unwrap().peel_to_commit().unwrap();
+        write_file(&repository, "a.txt", "b\n");
+        commit_all(&repository, "moved");
+        let moved = repository.head().unwrap().target().unwrap();
+
+        let tree = init.tree().unwrap();
+        let sig = repository.signature().unwrap();
+        assert!(create_commit(&repository, &sig, &sig, "stale", &tree, &[&init]).is_err());
+        assert_eq!(repository.head().unwrap().target(), Some(moved));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn files_staged_by_pre_commit_are_committed() {
+        use crate::git_operations::test_utils::write_hook;
+        let (_dir, repository) = init_repository();
+        write_file(&repository, "a.txt", "a\n");
+        commit_all(&repository, "init");
+        write_file(&repository, "a.txt", "a  \n");
+        let mut index = repository.index().unwrap();
+        index.add_path(std::path::Path::new("a.txt")).unwrap();
+        index.write().unwrap();
+        // Like a formatter, the hook fixes the staged file and stages it again
+        write_hook(&repository, "pre-commit", "#!/bin/sh\nprintf 'formatted\\n' > a.txt && git add a.txt\n");
+
+        commit(&repository, "format").unwrap();
+        let head = repository.head().unwrap().peel_to_tree().unwrap();
+        let blob = repository.find_blob(head.get_name("a.txt").unwrap().id()).unwrap();
+        assert_eq!(blob.content(), b"formatted\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn failing_pre_commit_aborts_the_commit() {
+        use crate::git_operations::test_utils::write_hook;
+        let (_dir, repository) = init_repository();
+        write_file(&repository, "a.txt", "a\n");
+        commit_all(&repository, "init");
+        write_file(&repository, "a.txt", "b\n");
+        let mut index = repository.index().unwrap();
+        index.add_path(std::path::Path::new("a.txt")).unwrap();
+        index.write().unwrap();
+        write_hook(&repository, "pre-commit", "#!/bin/sh\necho lint failed\nexit 1\n");
+
+        let error = commit(&repository, "rejected").unwrap_err();
+        assert_eq!(error.message(), "pre-commit hook failed\nlint failed");
+        assert_eq!(repository.head().unwrap().peel_to_commit().unwrap().summary(), Some("init"));
+    }
+}
@@ -0,0 +1,318 @@
+use crate::git_operations::index::{stage_file, unstage_file};
+use crate::git_operations::status::{FileStatus, StatusChange, StatusSection};
+use git2::{ApplyLocation, Delta, DiffFormat, DiffOptions, Repository};
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+/// Changes shown in the diff viewer, hunks are staged from the unstaged changes and
+/// unstaged from the staged ones
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DiffView {
+    /// Working tree and index against HEAD
+    #[default]
+    All,
+    /// Working tree against the index
+    Unstaged,
+    /// Index against HEAD
+    Staged,
+}
+
+impl DiffView {
+    pub fn next(&self) -> DiffView {
+        match self {
+            DiffView::All => DiffView::Unstaged,
+            DiffView::Unstaged => DiffView::Staged,
+            DiffView::Staged => DiffView::All,
+        }
+    }
+}
+
+impl Display for DiffView {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                DiffView::All => "all changes",
+                DiffView::Unstaged => "unstaged",
+                DiffView::Staged => "staged",
+            }
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffLineKind {
+    File,
+    Hunk,
+    Added,
+    Removed,
+    Context,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffLine {
+    pub kind: DiffLineKind,
+    /// Origin of the line as given by libgit2, like `+`, `-` or `H` for a hunk header
+    pub origin: char,
+    pub text: String,
+    /// Hunk the line belongs to, its header included
+    pub hunk: Option<usize>,
+}
+
+/// A file of the diff and the line its header starts on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffFile {
+    pub path: String,
+    pub old_path: Option<String>,
+    pub status: Delta,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffHunk {
+    pub file: usize,
+    pub line: usize,
+    pub old_start: u32,
+    pub new_start: u32,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct WorkingTreeDiff {
+    pub lines: Vec<DiffLine>,
+    pub files: Vec<DiffFile>,
+    pub hunks: Vec<DiffHunk>,
+}
+
+/// Unified diff of the given view of a repository, untracked files count as unstaged changes
+pub fn get_diff(repository: &Repository, view: DiffView) -> Result<WorkingTreeDiff, git2::Error> {
+    // An unborn branch has no tree, everything is shown as added
+    let head = repository.head().ok().and_then(|h| h.peel_to_tree().ok());
+    let mut opts = DiffOptions::new();
+    opts.include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .show_untracked_content(true);
+    let diff = match view {
+        DiffView::All => repository.diff_tree_to_workdir_with_index(head.as_ref(), Some(&mut opts))?,
+        DiffView::Unstaged => repository.diff_index_to_workdir(None, Some(&mut opts))?,
+        DiffView::Staged => repository.diff_tree_to_index(head.as_ref(), None, Some(&mut opts))?,
+    };
+
+    let mut result = WorkingTreeDiff::default();
+    diff.print(DiffFormat::Patch, |delta, hunk, line| {
+        let kind = match line.origin() {
+            'F' => DiffLineKind::File,
+            'H' => DiffLineKind::Hunk,
+            '+' => DiffLineKind::Added,
+            '-' => DiffLineKind::Removed,
+            _ => DiffLineKind::Context,
+        };
+        let content = String::from_utf8_lossy(line.content());
+
+        if kind == DiffLineKind::File {
+            let path = delta
+                .new_file()
+                .path()
+                .or_else(|| delta.old_file().path())
+                .map(|p| p.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let old_path = delta
+                .old_file()
+                .path()
+                .map(|p| p.to_string_lossy().to_string())
+                .filter(|p| *p != path);
+            result.files.push(DiffFile {
+                path,
+                old_path,
+                status: delta.status(),
+                line: result.lines.len(),
+            });
+        }
+        if let (DiffLineKind::Hunk, Some(hunk)) = (kind, &hunk) {
+            result.hunks.push(DiffHunk {
+                file: result.files.len().saturating_sub(1),
+                line: result.lines.len(),
+                old_start: hunk.old_start(),
+                new_start: hunk.new_start(),
+            });
+        }
+        let hunk = Some(result.hunks.len())
+            .filter(|_| kind != DiffLineKind::File && hunk.is_some())
+            .map(|n| n - 1);
+
+        // One line per line of the diff, blank ones included. Only file headers span several
+        // lines, and the end of file markers come wrapped in newlines of their own.
+        let texts: Vec<String> = match line.origin() {
+            'F' => content.trim_end_matches('\n').split('\n').map(|t| t.to_string()).collect(),
+            '=' | '>' | '<' => vec![content.trim_matches('\n').to_string()],
+            origin @ ('+' | '-' | ' ') => {
+                vec![format!("{}{}", origin, content.strip_suffix('\n').unwrap_or(&content))]
+            }
+            _ => vec![content.strip_suffix('\n').unwrap_or(&content).to_string()],
+        };
+        texts.into_iter().for_each(|text| {
+            result.lines.push(DiffLine {
+                kind,
+                origin: line.origin(),
+                text,
+                hunk,
+            });
+        });
+        true
+    })?;
+    Ok(result)
+}
+
+/// Builds a patch of one hunk keeping only the selected lines, every line when none is selected.
+/// Reversed, the patch takes the selected lines out of the new side of the diff.
+fn build_hunk_patch(diff: &WorkingTreeDiff, hunk_index: usize, selected: &[usize], reverse: bool) -> Option<String> {
+    let hunk = diff.hunks.get(hunk_index)?;
+    let path = &diff.files.get(hunk.file)?.path;
+    let start = if reverse { hunk.new_start } else { hunk.old_start };
+
+    let (mut old_lines, mut new_lines, mut changes) = (0, 0, 0);
+    let mut body = String::new();
+    let mut last_kept = false;
+    for (i, line) in diff.lines.iter().enumerate().filter(|(_, l)| l.hunk == Some(hunk_index)) {
+        let origin = match (line.origin, reverse) {
+            ('+', false) | ('-', true) => '+',
+            ('-', false) | ('+', true) => '-',
+            (' ', _) => ' ',
+            // Markers of a missing newline at the end of the file follow the line they belong to
+            ('=' | '>' | '<', _) => {
+                if last_kept {
+                    body.push_str("\\ No newline at end of file\n");
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        let content = &line.text[1..];
+        let chosen = selected.is_empty() || selected.contains(&i);
+        last_kept = true;
+
+        match origin {
+            '+' if chosen => {
+                body.push_str(&format!("+{}\n", content));
+                new_lines += 1;
+                changes += 1;
+            }
+            // An added line that is not chosen is left out of the patch
+            '+' => last_kept = false,
+            '-' if chosen => {
+                body.push_str(&format!("-{}\n", content));
+                old_lines += 1;
+                changes += 1;
+            }
+            // A removed line that is not chosen stays, as context
+            _ => {
+                body.push_str(&format!(" {}\n", content));
+                old_lines += 1;
+                new_lines += 1;
+            }
+        }
+    }
+
+    if changes == 0 {
+        return None;
+    }
+    Some(format!(
+        "diff --git a/{path} b/{path}\n--- a/{path}\n+++ b/{path}\n@@ -{start},{old_lines} +{start},{new_lines} @@\n{body}",
+        path = path,
+        start = start,
+        old_lines = old_lines,
+        new_lines = new_lines,
+        body = body
+    ))
+}
+
+/// Stages the selected lines of a hunk of the unstaged changes, or unstages them from the staged
+/// changes. Files that are added, deleted or renamed are staged and unstaged as a whole.
+pub fn apply_hunk(
+    repository: &Repository,
+    diff: &WorkingTreeDiff,
+    view: DiffView,
+    hunk_index: usize,
+    selected: &[usize],
+) -> Result<String, git2::Error> {
+    let stage = match view {
+        DiffView::Unstaged => true,
+        DiffView::Staged => false,
+        DiffView::All => {
+            return Err(git2::Error::from_str("Switch to the unstaged or staged changes with v first"));
+        }
+    };
+    let hunk = diff
+        .hunks
+        .get(hunk_index)
+        .ok_or_else(|| git2::Error::from_str("Please select a hunk!"))?;
+    let file = &diff.files[hunk.file];
+
+    if file.status != Delta::Modified {
+        let file = FileStatus {
+            path: file.path.clone(),
+            old_path: file.old_path.clone(),
+            section: if stage { StatusSection::Unstaged } else { StatusSection::Staged },
+            change: if file.status == Delta::Deleted { StatusChange::Deleted } else { StatusChange::Modified },
+        };
+        return if stage { stage_file(repository, &file) } else { unstage_file(repository, &file) };
+    }
+
+    let patch = build_hunk_patch(diff, hunk_index, selected, !stage)
+        .ok_or_else(|| git2::Error::from_str("Select added or removed lines"))?;
+    let patch = git2::Diff::from_buffer(patch.as_bytes())?;
+    repository.apply(&patch, ApplyLocation::Index, None)?;
+    Ok(if stage {
+        String::from("Hunk is staged!")
+    } else {
+        String::from("Hunk is unstaged!")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git_operations::test_utils::{commit_all, init_repository, read_index, write_file};
+
+    fn repository_with_blank_lines() -> (tempfile::TempDir, Repository) {
+        let (dir, repository) = init_repository();
+        write_file(&repository, "f.txt", "a\n\nb\n\nc\n");
+        commit_all(&repository, "init");
+        write_file(&repository, "f.txt", "a\n\nB\n\n\nc\n");
+        (dir, repository)
+    }
+
+    #[test]
+    fn blank_lines_are_kept() {
+        let (_dir, repository) = repository_with_blank_lines();
+        let diff = get_diff(&repository, DiffView::Unstaged).unwrap();
+        let hunk: Vec<&str> = diff
+            .lines
+            .iter()
+            .filter(|l| l.hunk == Some(0) && l.kind != DiffLineKind::Hunk)
+            .map(|l| l.text.as_str())
+            .collect();
+        assert_eq!(hunk, vec![" a", " ", "-b", "+B", "+", " ", " c"]);
+    }
+
+    #[test]
+    fn hunk_with_blank_lines_is_staged() {
+        let (_dir, repository) = repository_with_blank_lines();
+        let diff = get_diff(&repository, DiffView::Unstaged).unwrap();
+        apply_hunk(&repository, &diff, DiffView::Unstaged, 0, &[]).unwrap();
+        assert_eq!(read_index(&repository, "f.txt").unwrap(), "a\n\nB\n\n\nc\n");
+
+        let staged = get_diff(&repository, DiffView::Staged).unwrap();
+        apply_hunk(&repository, &staged, DiffView::Staged, 0, &[]).unwrap();
+        assert_eq!(read_index(&repository, "f.txt").unwrap(), "a\n\nb\n\nc\n");
+    }
+
+    #[test]
+    fn blank_added_line_is_staged_alone() {
+        let (_dir, repository) = repository_with_blank_lines();
+        let diff = get_diff(&repository, DiffView::Unstaged).unwrap();
+        let blank = diff.lines.iter().position(|l| l.text == "+").unwrap();
+        apply_hunk(&repository, &diff, DiffView::Unstaged, 0, &[blank]).unwrap();
+        assert_eq!(read_index(&repository, "f.txt").unwrap(), "a\n\nb\n\n\nc\n");
+    }
+}
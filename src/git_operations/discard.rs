@@ -0,0 +1,248 @@
+use crate::git_operations::status::{FileStatus, StatusSection};
+use git2::build::CheckoutBuilder;
+use git2::{Repository, StatusOptions};
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::Path;
+
+/// Throws away the changes to a file: unstaged changes are checked out from the index, staged
+/// ones from HEAD. A file HEAD doesn't have is removed from the index and the working tree.
+pub fn discard_file(repository: &Repository, file: &FileStatus) -> Result<String, git2::Error> {
+    let workdir = repository
+        .workdir()
+        .ok_or_else(|| git2::Error::from_str("Repository has no working tree"))?;
+
+    match file.section {
+        StatusSection::Untracked => Err(git2::Error::from_str("Untracked files are removed with :clean")),
+        StatusSection::Unstaged => {
+            repository.checkout_index(None, Some(CheckoutBuilder::new().force().path(&file.path)))?;
+            Ok(format!("Changes to {} are discarded!", file.path))
+        }
+        StatusSection::Staged | StatusSection::Conflicted => {
+            let head = repository.head().and_then(|h| h.peel_to_tree()).ok();
+            let mut index = repository.index()?;
+            let mut checkout = CheckoutBuilder::new();
+            checkout.force();
+            let mut restored = false;
+
+            for path in std::iter::once(&file.path).chain(file.old_path.as_ref()) {
+                if head.as_ref().is_some_and(|h| h.get_path(Path::new(path)).is_ok()) {
+                    checkout.path(path);
+                    restored = true;
+                } else {
+                    index.remove_path(Path::new(path))?;
+                    remove_entry(&workdir.join(path))?;
+                }
+            }
+            index.write()?;
+            if let (Some(head), true) = (head, restored) {
+                repository.checkout_tree(head.as_object(), Some(&mut checkout))?;
+            }
+            Ok(format!("Changes to {} are discarded!", file.path))
+        }
+    }
+}
+
+/// A file or folder `:clean` deletes, folders end with a `/`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CleanEntry {
+    pub path: String,
+    pub ignored: bool,
+}
+
+impl Display for CleanEntry {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{:<10}{}", if self.ignored { "ignored" } else { "untracked" }, self.path)
+    }
+}
+
+/// Untracked files and folders of the working tree, and the ignored ones too when `ignored` is set,
+/// like `git clean -d [-x] --dry-run`. Folders holding a repository are left out, git doesn't
+/// clean them either.
+pub fn get_clean_entries(repository: &Repository, ignored: bool) -> Result<Vec<CleanEntry>, git2::Error> {
+    let workdir = repository
+        .workdir()
+        .ok_or_else(|| git2::Error::from_str("Repository has no working tree"))?;
+    let mut opts = StatusOptions::new();
+    opts.include_untracked(true)
+        .include_ignored(ignored)
+        .recurse_untracked_dirs(false)
+        .recurse_ignored_dirs(false)
+        .exclude_submodules(true);
+
+    let mut entries: Vec<CleanEntry> = repository
+        .statuses(Some(&mut opts))?
+        .iter()
+        .filter(|s| s.status().is_wt_new() || s.status().is_ignored())
+        .filter_map(|s| {
+            Some(CleanEntry {
+                path: s.path()?.to_string(),
+                ignored: s.status().is_ignored(),
+            })
+        })
+        .filter(|e| !contains_repository(&workdir.join(&e.path)))
+        .collect();
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(entries)
+}
+
+/// Deletes the entries listed by `get_clean_entries`
+pub fn clean_entries(repository: &Repository, entries: &[CleanEntry]) -> Result<String, git2::Error> {
+    let workdir = repository
+        .workdir()
+        .ok_or_else(|| git2::Error::from_str("Repository has no working tree"))?;
+    entries
+        .iter()
+        .try_for_each(|e| remove_entry(&workdir.join(&e.path)))?;
+    Ok(format!("{} are removed!", count_entries(entries)))
+}
+
+/// Untracked and ignored entries counted apart, like `2 untracked and 1 ignored entries`
+pub fn count_entries(entries: &[CleanEntry]) -> String {
+    let ignored = entries.iter().filter(|e| e.ignored).count();
+    match (entries.len() - ignored, ignored) {
+        (untracked, 0) => format!("{} untracked entries", untracked),
+        (0, ignored) => format!("{} ignored entries", ignored),
+        (untracked, ignored) => format!("{} untracked and {} ignored entries", untracked, ignored),
+    }
+}
+
+/// Whether a folder is or holds a repository, symbolic links are not followed
+fn contains_repository(path: &Path) -> bool {
+    if !fs::symlink_metadata(path).is_ok_and(|m| m.is_dir()) {
+        return false;
+    }
+    if path.join(".git").exists() {
+        return true;
+    }
+    fs::read_dir(path)
+        .map(|entries| entries.flatten().any(|e| contains_repository(&e.path())))
+        .unwrap_or(false)
+}
+
+/// Removes a file, a symbolic link or a whole folder, an entry already gone is not an error
+fn remove_entry(path: &Path) -> Result<(), git2::Error> {
+    let result = match fs::symlink_metadata(path) {
+        Ok(m) if m.is_dir() => fs::remove_dir_all(path),
+        Ok(_) => fs::remove_file(path),
+        Err(_) => Ok(()),
+    };
+    result.map_err(|e| git2::Error::from_str(&format!("Could not remove {}: {}", path.display(), e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git_operations::status::StatusChange;
+    use crate::git_operations::test_utils::{commit_all, init_repository, read_index, write_file};
+
+    fn file_status(path: &str, old_path: Option<&str>, section: StatusSection, change: StatusChange) -> FileStatus {
+        FileStatus {
+            path: path.to_string(),
+            old_path: old_path.map(|p| p.to_string()),
+            section,
+            change,
+        }
+    }
+
+    fn read_file(repository: &Repository, path: &str) -> Option<String> {
+        fs::read_to_string(repository.workdir().unwrap().join(path)).ok()
+    }
+
+    #[test]
+    fn staged_new_file_is_removed() {
+        let (_dir, repository) = init_repository();
+        write_file(&repository, "a.txt", "a\n");
+        commit_all(&repository, "init");
+        write_file(&repository, "new.txt", "new\n");
+        let mut index = repository.index().unwrap();
+        index.add_path(Path::new("new.txt")).unwrap();
+        index.write().unwrap();
+
+        let file = file_status("new.txt", None, StatusSection::Staged, StatusChange::New);
+        discard_file(&repository, &file).unwrap();
+        assert_eq!(read_index(&repository, "new.txt"), None);
+        assert_eq!(read_file(&repository, "new.txt"), None);
+        assert_eq!(read_file(&repository, "a.txt").as_deref(), Some("a\n"));
+    }
+
+    #[test]
+    fn staged_rename_is_undone() {
+        let (_dir, repository) = init_repository();
+        write_file(&repository, "old.txt", "content\n");
+        commit_all(&repository, "init");
+        fs::rename(
+            repository.workdir().unwrap().join("old.txt"),
+            repository.workdir().unwrap().join("new.txt"),
+        )
+        .unwrap();
+        let mut index = repository.index().unwrap();
+        index.remove_path(Path::new("old.txt")).unwrap();
+        index.add_path(Path::new("new.txt")).unwrap();
+        index.write().unwrap();
+
+        let file = file_status("new.txt", Some("old.txt"), StatusSection::Staged, StatusChange::Renamed);
+        discard_file(&repository, &file).unwrap();
+        assert_eq!(read_index(&repository, "old.txt").as_deref(), Some("content\n"));
+        assert_eq!(read_file(&repository, "old.txt").as_deref(), Some("content\n"));
+        assert_eq!(read_index(&repository, "new.txt"), None);
+        assert_eq!(read_file(&repository, "new.txt"), None);
+    }
+
+    #[test]
+    fn unstaged_change_is_checked_out_from_the_index() {
+        let (_dir, repository) = init_repository();
+        write_file(&repository, "a.txt", "a\n");
+        commit_all(&repository, "init");
+        write_file(&repository, "a.txt", "staged\n");
+        let mut index = repository.index().unwrap();
+        index.add_path(Path::new("a.txt")).unwrap();
+        index.write().unwrap();
+        write_file(&repository, "a.txt", "unstaged\n");
+
+        let file = file_status("a.txt", None, StatusSection::Unstaged, StatusChange::Modified);
+        discard_file(&repository, &file).unwrap();
+        assert_eq!(read_file(&repository, "a.txt").as_deref(), Some("staged\n"));
+        assert_eq!(read_index(&repository, "a.txt").as_deref(), Some("staged\n"));
+    }
+
+    #[test]
+    fn untracked_file_is_not_discarded() {
+        let (_dir, repository) = init_repository();
+        write_file(&repository, "new.txt", "new\n");
+        let file = file_status("new.txt", None, StatusSection::Untracked, StatusChange::New);
+        assert!(discard_file(&repository, &file).is_err());
+        assert!(read_file(&repository, "new.txt").is_some());
+    }
+
+    #[test]
+    fn nested_repositories_are_not_cleaned() {
+        let (_dir, repository) = init_repository();
+        write_file(&repository, ".gitignore", "*.log\n");
+        write_file(&repository, "a.txt", "a\n");
+        commit_all(&repository, "init");
+        write_file(&repository, "untracked.txt", "u\n");
+        write_file(&repository, "build.log", "log\n");
+        write_file(&repository, "folder/file.txt", "f\n");
+        Repository::init(repository.workdir().unwrap().join("libs/nested")).unwrap();
+
+        let untracked = get_clean_entries(&repository, false).unwrap();
+        let paths: Vec<&str> = untracked.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, ["folder/", "untracked.txt"]);
+        assert_eq!(count_entries(&untracked), "2 untracked entries");
+
+        let all = get_clean_entries(&repository, true).unwrap();
+        assert!(all.contains(&CleanEntry {
+            path: "build.log".to_string(),
+            ignored: true
+        }));
+        assert_eq!(count_entries(&all), "2 untracked and 1 ignored entries");
+
+        clean_entries(&repository, &all).unwrap();
+        assert_eq!(read_file(&repository, "build.log"), None);
+        assert_eq!(read_file(&repository, "untracked.txt"), None);
+        assert!(repository.workdir().unwrap().join("libs/nested/.git").exists());
+        assert!(read_file(&repository, "a.txt").is_some());
+    }
+}
@@ -0,0 +1,94 @@
+use git2::{Oid, Remote, Repository};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Folder the hooks of a repository are in, `core.hooksPath` when it is set. Linked worktrees
+/// share the hooks of their main repository.
+fn hooks_dir(repository: &Repository) -> PathBuf {
+    let base = repository.workdir().unwrap_or_else(|| repository.path());
+    if let Ok(path) = repository.config().and_then(|c| c.get_path("core.hooksPath")) {
+        return if path.is_absolute() { path } else { base.join(path) };
+    }
+
+    let common_dir = fs::read_to_string(repository.path().join("commondir"))
+        .ok()
+        .map(|c| repository.path().join(c.trim()))
+        .unwrap_or_else(|| repository.path().to_path_buf());
+    common_dir.join("hooks")
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// Whether the repository has an executable hook with that name, git skips the others
+pub fn has_hook(repository: &Repository, name: &str) -> bool {
+    is_executable(&hooks_dir(repository).join(name))
+}
+
+/// Runs a hook when the repository has it, its output is returned in the error when it fails
+pub fn run_hook(repository: &Repository, name: &str, args: &[&str], stdin: Option<&str>) -> Result<(), git2::Error> {
+    if !has_hook(repository, name) {
+        return Ok(());
+    }
+    let hook = hooks_dir(repository).join(name);
+
+    let mut child = Command::new(&hook)
+        .args(args)
+        .current_dir(repository.workdir().unwrap_or_else(|| repository.path()))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| git2::Error::from_str(&format!("{} hook could not run: {}", name, e)))?;
+    if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+        // A hook that doesn't read its input closes the pipe, that is not an error
+        let _ = pipe.write_all(input.as_bytes());
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| git2::Error::from_str(&format!("{} hook could not run: {}", name, e)))?;
+
+    if output.status.success() {
+        return Ok(());
+    }
+    let mut message = format!("{} hook failed", name);
+    [&output.stdout, &output.stderr]
+        .iter()
+        .flat_map(|o| String::from_utf8_lossy(o).lines().map(|l| l.to_string()).collect::<Vec<String>>())
+        .filter(|l| !l.trim().is_empty())
+        .for_each(|l| message.push_str(&format!("\n{}", l)));
+    Err(git2::Error::from_str(&message))
+}
+
+/// Runs a hook that can't abort the operation anymore, its failure is returned as a note
+/// to add to the result of the operation
+pub fn run_post_hook(repository: &Repository, name: &str, args: &[&str]) -> String {
+    match run_hook(repository, name, args, None) {
+        Ok(()) => String::new(),
+        Err(e) => format!("\n{}", e.message()),
+    }
+}
+
+/// Runs the pre-push hook before pushing a reference to the same name on a remote, what the
+/// remote has is taken from its tracking branch
+pub fn run_pre_push_hook(repository: &Repository, remote: &Remote, reference: &str) -> Result<(), git2::Error> {
+    let name = remote.name().unwrap_or_default();
+    let url = remote.pushurl().or_else(|| remote.url()).unwrap_or(name);
+    let local = repository.refname_to_id(reference)?;
+    let remote_oid = reference
+        .strip_prefix("refs/heads/")
+        .and_then(|b| repository.refname_to_id(&format!("refs/remotes/{}/{}", name, b)).ok())
+        .unwrap_or_else(Oid::zero);
+    let stdin = format!("{} {} {} {}\n", reference, local, reference, remote_oid);
+    run_hook(repository, "pre-push", &[name, url], Some(&stdin))
+}
@@ -0,0 +1,154 @@
+use crate::git_operations::status::{FileStatus, StatusChange};
+use git2::{IndexAddOption, Repository, ResetType};
+use std::path::Path;
+
+/// Adds a file of the working tree status to the index, a deleted file is removed from it
+pub fn stage_file(repository: &Repository, file: &FileStatus) -> Result<String, git2::Error> {
+    let mut index = repository.index()?;
+    if file.change == StatusChange::Deleted {
+        index.remove_path(Path::new(&file.path))?;
+    } else {
+        index.add_path(Path::new(&file.path))?;
+    }
+    if let Some(old_path) = &file.old_path {
+        index.remove_path(Path::new(old_path))?;
+    }
+    index.write()?;
+    Ok(format!("{} is staged!", file.path))
+}
+
+/// Puts the index entry of a file back to its HEAD version, like `git reset -- <path>`
+pub fn unstage_file(repository: &Repository, file: &FileStatus) -> Result<String, git2::Error> {
+    let paths: Vec<&str> = std::iter::once(file.path.as_str())
+        .chain(file.old_path.as_deref())
+        .collect();
+
+    match repository.head().and_then(|h| h.peel_to_commit()) {
+        Ok(head) => repository.reset_default(Some(head.as_object()), paths)?,
+        // Nothing is committed on an unborn branch, the file leaves the index
+        Err(_) => {
+            let mut index = repository.index()?;
+            paths.iter().try_for_each(|p| index.remove_path(Path::new(p)))?;
+            index.write()?;
+        }
+    }
+    Ok(format!("{} is unstaged!", file.path))
+}
+
+/// Stages every change of the working tree, deletions and untracked files included
+pub fn stage_all(repository: &Repository) -> Result<String, git2::Error> {
+    let mut index = repository.index()?;
+    index.add_all(["*"].iter(), IndexAddOption::DEFAULT, None)?;
+    index.update_all(["*"].iter(), None)?;
+    index.write()?;
+    Ok(String::from("Every change is staged!"))
+}
+
+/// Resets the index to HEAD, the working tree is left untouched
+pub fn unstage_all(repository: &Repository) -> Result<String, git2::Error> {
+    match repository.head().and_then(|h| h.peel_to_commit()) {
+        Ok(head) => repository.reset(head.as_object(), ResetType::Mixed, None)?,
+        Err(_) => {
+            let mut index = repository.index()?;
+            index.clear()?;
+            index.write()?;
+        }
+    }
+    Ok(String::from("Every change is unstaged!"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git_operations::status::{get_repository_status, StatusSection};
+    use crate::git_operations::test_utils::{commit_all, init_repository, read_index, write_file};
+    use std::fs;
+
+    fn file(path: &str, old_path: Option<&str>, section: StatusSection, change: StatusChange) -> FileStatus {
+        FileStatus {
+            path: path.to_string(),
+            old_path: old_path.map(|p| p.to_string()),
+            section,
+            change,
+        }
+    }
+
+    #[test]
+    fn deletion_is_staged_and_unstaged() {
+        let (_dir, repository) = init_repository();
+        write_file(&repository, "a.txt", "a\n");
+        commit_all(&repository, "init");
+        fs::remove_file(repository.workdir().unwrap().join("a.txt")).unwrap();
+
+        stage_file(&repository, &file("a.txt", None, StatusSection::Unstaged, StatusChange::Deleted)).unwrap();
+        assert_eq!(read_index(&repository, "a.txt"), None);
+        unstage_file(&repository, &file("a.txt", None, StatusSection::Staged, StatusChange::Deleted)).unwrap();
+        assert_eq!(read_index(&repository, "a.txt").as_deref(), Some("a\n"));
+    }
+
+    #[test]
+    fn rename_is_staged_and_unstaged_on_both_paths() {
+        let (_dir, repository) = init_repository();
+        write_file(&repository, "old.txt", "a file long enough to be found as renamed\n");
+        commit_all(&repository, "init");
+        let workdir = repository.workdir().unwrap();
+        fs::rename(workdir.join("old.txt"), workdir.join("new.txt")).unwrap();
+        let mut index = repository.index().unwrap();
+        index.add_path(Path::new("new.txt")).unwrap();
+        index.write().unwrap();
+
+        // With the new path staged alone the status shows the deletion of the old one unstaged
+        let rename = file("new.txt", Some("old.txt"), StatusSection::Unstaged, StatusChange::Renamed);
+        stage_file(&repository, &rename).unwrap();
+        assert_eq!(read_index(&repository, "old.txt"), None);
+        assert!(read_index(&repository, "new.txt").is_some());
+        let staged = get_repository_status(&Repository::open(repository.path()).ok());
+        assert_eq!(
+            staged,
+            [file("new.txt", Some("old.txt"), StatusSection::Staged, StatusChange::Renamed)]
+        );
+
+        unstage_file(&repository, &staged[0]).unwrap();
+        assert!(read_index(&repository, "old.txt").is_some());
+        assert_eq!(read_index(&repository, "new.txt"), None);
+    }
+
+    #[test]
+    fn files_leave_the_index_of_an_unborn_branch() {
+        let (_dir, repository) = init_repository();
+        write_file(&repository, "a.txt", "a\n");
+        write_file(&repository, "b.txt", "b\n");
+
+        stage_file(&repository, &file("a.txt", None, StatusSection::Untracked, StatusChange::New)).unwrap();
+        assert_eq!(read_index(&repository, "a.txt").as_deref(), Some("a\n"));
+        unstage_file(&repository, &file("a.txt", None, StatusSection::Staged, StatusChange::New)).unwrap();
+        assert_eq!(read_index(&repository, "a.txt"), None);
+
+        stage_all(&repository).unwrap();
+        assert!(read_index(&repository, "a.txt").is_some() && read_index(&repository, "b.txt").is_some());
+        unstage_all(&repository).unwrap();
+        assert!(repository.index().unwrap().is_empty());
+        assert!(repository.workdir().unwrap().join("a.txt").exists());
+    }
+
+    #[test]
+    fn every_change_is_staged_and_unstaged() {
+        let (_dir, repository) = init_repository();
+        write_file(&repository, "a.txt", "a\n");
+        write_file(&repository, "b.txt", "b\n");
+        commit_all(&repository, "init");
+        write_file(&repository, "a.txt", "changed\n");
+        fs::remove_file(repository.workdir().unwrap().join("b.txt")).unwrap();
+        write_file(&repository, "c.txt", "c\n");
+
+        stage_all(&repository).unwrap();
+        assert_eq!(read_index(&repository, "a.txt").as_deref(), Some("changed\n"));
+        assert_eq!(read_index(&repository, "b.txt"), None);
+        assert_eq!(read_index(&repository, "c.txt").as_deref(), Some("c\n"));
+
+        unstage_all(&repository).unwrap();
+        assert_eq!(read_index(&repository, "a.txt").as_deref(), Some("a\n"));
+        assert_eq!(read_index(&repository, "b.txt").as_deref(), Some("b\n"));
+        assert_eq!(read_index(&repository, "c.txt"), None);
+    }
+}
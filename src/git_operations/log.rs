@@ -76,12 +76,12 @@ fn print_time(time: &Time, prefix: &str) -> String {
     let datetime: DateTime<Utc> = DateTime::from_utc(naive, Utc);
     let time = datetime.format("%Y-%m-%d %H:%M:%S");
 
-    return format!(
+    format!(
         "{}{} {}{:02}{:02}",
         prefix,
         time,
         sign,
         hours,
         minutes
-    );
+    )
 }
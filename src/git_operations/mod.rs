@@ -1,3 +1,18 @@
+pub mod clone;
+pub mod commit;
+pub mod diff;
+pub mod discard;
+pub mod hooks;
+pub mod index;
 pub mod log;
 pub mod pull;
-pub mod repo;
\ No newline at end of file
+pub mod repo;
+pub mod sign;
+pub mod stash;
+pub mod state;
+pub mod submodule;
+pub mod tag;
+pub mod status;
+
+#[cfg(test)]
+mod test_utils;
@@ -1,9 +1,12 @@
+use crate::git_operations::commit::{create_commit, run_commit_msg_hook};
+use crate::git_operations::hooks::run_post_hook;
 use crate::git_operations::repo::git_credentials_callback;
 use git2::{AutotagOption, FetchOptions, FetchPrune, Remote, RemoteCallbacks, Repository};
 
 pub fn do_fetch<'a>(
     repo: &'a Repository,
     refs: &[&str],
+    remote_name: &str,
     remote: &'a mut Remote,
 ) -> Result<git2::AnnotatedCommit<'a>, git2::Error> {
     let mut cb = RemoteCallbacks::new();
@@ -18,8 +21,8 @@ pub fn do_fetch<'a>(
 
     remote.fetch(refs, Some(&mut fo), None)?;
 
-    let fetch_head = repo.find_reference(&format!("refs/remotes/origin/{}", refs[0]))?;
-    Ok(repo.reference_to_annotated_commit(&fetch_head).unwrap())
+    let fetch_head = repo.find_reference(&format!("refs/remotes/{}/{}", remote_name, refs[0]))?;
+    repo.reference_to_annotated_commit(&fetch_head)
 }
 
 fn fast_forward(
@@ -48,7 +51,7 @@ fn normal_merge(
     repo: &Repository,
     local: &git2::AnnotatedCommit,
     remote: &git2::AnnotatedCommit,
-) -> Result<(), git2::Error> {
+) -> Result<bool, git2::Error> {
     let local_tree = repo.find_commit(local.id())?.tree()?;
     let remote_tree = repo.find_commit(remote.id())?.tree()?;
     let ancestor = repo
@@ -57,27 +60,21 @@ fn normal_merge(
     let mut idx = repo.merge_trees(&ancestor, &local_tree, &remote_tree, None)?;
 
     if idx.has_conflicts() {
-        repo.checkout_index(Some(&mut idx), None)?;
-        return Ok(());
+        // Merge again through the repository so MERGE_HEAD is written and the merge can be continued
+        repo.merge(&[remote], None, None)?;
+        return Ok(true);
     }
     let result_tree = repo.find_tree(idx.write_tree_to(repo)?)?;
     // now create the merge commit
-    let msg = format!("Merge: {} into {}", remote.id(), local.id());
+    let msg = run_commit_msg_hook(repo, &format!("Merge: {} into {}", remote.id(), local.id()))?;
     let sig = repo.signature()?;
     let local_commit = repo.find_commit(local.id())?;
     let remote_commit = repo.find_commit(remote.id())?;
     // Do our merge commit and set current branch head to that commit.
-    let _merge_commit = repo.commit(
-        Some("HEAD"),
-        &sig,
-        &sig,
-        &msg,
-        &result_tree,
-        &[&local_commit, &remote_commit],
-    )?;
+    let _merge_commit = create_commit(repo, &sig, &sig, &msg, &result_tree, &[&local_commit, &remote_commit])?;
     // Set working tree to match head.
     repo.checkout_head(None)?;
-    Ok(())
+    Ok(false)
 }
 
 pub fn do_merge<'a>(
@@ -85,13 +82,13 @@ pub fn do_merge<'a>(
     remote_branch: &str,
     fetch_commit: git2::AnnotatedCommit<'a>,
 ) -> Result<String, git2::Error> {
-    let mut msg: &str = "";
+    let mut msg = String::new();
     // 1. do a merge analysis
     let analysis = repo.merge_analysis(&[&fetch_commit])?;
 
     // 2. Do the appopriate merge
     if analysis.0.is_fast_forward() {
-        msg = "Doing a fast forward";
+        msg = String::from("Doing a fast forward");
         // do a fast forward
         let refname = format!("refs/heads/{}", remote_branch);
         match repo.find_reference(&refname) {
@@ -120,11 +117,15 @@ pub fn do_merge<'a>(
     } else if analysis.0.is_normal() {
         // do a normal merge
         let head_commit = repo.reference_to_annotated_commit(&repo.head()?)?;
-        normal_merge(repo, &head_commit, &fetch_commit)?;
+        if normal_merge(repo, &head_commit, &fetch_commit)? {
+            return Ok(String::from("Merge has conflicts, resolve them and :continue"));
+        }
     } else {
-        msg = "Nothing to do...";
+        return Ok(String::from("Nothing to do..."));
     }
-    Ok(msg.to_string())
+    // The merge is not a squash, which is what the argument of post-merge tells
+    msg.push_str(&run_post_hook(repo, "post-merge", &["0"]));
+    Ok(msg)
 }
 
 pub fn fetch_repository_from_remote(
@@ -135,7 +136,7 @@ pub fn fetch_repository_from_remote(
     match repository.find_remote(remote_name) {
         Ok(mut remote) => {
             let result =
-                if let Ok(fetch_commit) = do_fetch(repository, &[remote_branch], &mut remote) {
+                if let Ok(fetch_commit) = do_fetch(repository, &[remote_branch], remote_name, &mut remote) {
                     do_merge(repository, remote_branch, fetch_commit)
                 } else {
                     Err(git2::Error::from_str("Could not find remote branch!"))
@@ -166,3 +167,27 @@ pub fn fetch_all(remote: &mut Remote) -> Result<String, git2::Error> {
     remote.update_tips(None, true, AutotagOption::Unspecified, None)?;
     Ok(String::from("Fetching is done!"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git_operations::test_utils::{commit_all, init_repository, write_file};
+
+    #[test]
+    fn tracking_branch_of_the_pulled_remote_is_merged() {
+        let (_upstream_dir, upstream) = init_repository();
+        write_file(&upstream, "a.txt", "a\n");
+        commit_all(&upstream, "init");
+        let branch = upstream.head().unwrap().shorthand().unwrap().to_string();
+
+        let local_dir = tempfile::TempDir::new().unwrap();
+        let local = Repository::clone(upstream.workdir().unwrap().to_str().unwrap(), local_dir.path()).unwrap();
+        local.remote_rename("origin", "upstream").unwrap();
+        write_file(&upstream, "b.txt", "b\n");
+        commit_all(&upstream, "second");
+
+        fetch_repository_from_remote("upstream", &branch, &local).unwrap();
+        assert_eq!(local.head().unwrap().target(), upstream.head().unwrap().target());
+        assert!(local_dir.path().join("b.txt").exists());
+    }
+}
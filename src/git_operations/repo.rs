@@ -1,5 +1,9 @@
-use git2::{Cred, CredentialType, Repository};
-use std::path::{PathBuf};
+use crate::git_operations::commit::create_commit;
+use git2::{Branch, BranchType, Cred, CredentialType, DescribeOptions, ErrorCode, IndexAddOption, Repository};
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
 
 pub fn git_credentials_callback(
     _url: &str,
@@ -29,10 +33,7 @@ pub fn is_repository(path: PathBuf) -> bool {
 }
 
 pub fn get_repository(path: &PathBuf) -> Option<Repository> {
-    match Repository::open(path) {
-        Ok(repo) => Some(repo),
-        Err(_e) => None,
-    }
+    Repository::open(path).ok()
 }
 
 pub fn get_repository_tags(repository: &Option<Repository>) -> Vec<String> {
@@ -53,10 +54,7 @@ pub fn get_repository_branches(repository: &Option<Repository>) -> Vec<String> {
     let mut branches_string = Vec::new();
 
     if let Some(r) = repository {
-        let branches = match r.branches(None) {
-            Ok(branches) => Some(branches),
-            Err(_) => None,
-        };
+        let branches = r.branches(None).ok();
 
         branches.unwrap().for_each(|b| {
             let b1 = b.unwrap().0.name().unwrap().unwrap().to_string();
@@ -66,27 +64,293 @@ pub fn get_repository_branches(repository: &Option<Repository>) -> Vec<String> {
     branches_string
 }
 
-pub fn get_repository_active_branch(repository: &Option<Repository>) -> String {
-    let mut branch_id: String = "".to_string();
-    if let Some(r) = repository {
-        if let Ok(head) = r.head() {
-            if let Some(name) = head.name() {
-                branch_id = name.replace("refs/heads/", "")
-            }
-        } else {
-            branch_id = "empty".to_string()
+/// Where HEAD of a repository points to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Head {
+    Branch(String),
+    /// HEAD is on a commit, `describe` names it after the closest tag when there is one
+    Detached { short_sha: String, describe: Option<String> },
+    /// The branch HEAD points to has no commit yet
+    Unborn(String),
+}
+
+impl Head {
+    /// Branch HEAD points to, an unborn branch included
+    pub fn branch_name(&self) -> Option<&str> {
+        match self {
+            Head::Branch(name) | Head::Unborn(name) => Some(name),
+            Head::Detached { .. } => None,
+        }
+    }
+}
+
+impl Display for Head {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Head::Branch(name) => write!(f, "{}", name),
+            Head::Detached { describe: Some(describe), .. } => write!(f, "detached {}", describe),
+            Head::Detached { short_sha, .. } => write!(f, "detached {}", short_sha),
+            Head::Unborn(name) => write!(f, "{} (no commits)", name),
+        }
+    }
+}
+
+pub fn get_repository_head(repository: &Option<Repository>) -> Option<Head> {
+    let r = repository.as_ref()?;
+    let head = match r.head() {
+        Ok(head) => head,
+        Err(e) if e.code() == ErrorCode::UnbornBranch => {
+            let target = r.find_reference("HEAD").ok()?.symbolic_target()?.to_string();
+            return Some(Head::Unborn(target.trim_start_matches("refs/heads/").to_string()));
         }
+        Err(_) => return None,
+    };
+
+    if head.is_branch() {
+        return head.shorthand().map(|name| Head::Branch(name.to_string()));
+    }
+
+    let commit = head.peel_to_commit().ok()?;
+    let short_sha = commit.as_object().short_id().ok()?.as_str()?.to_string();
+    let describe = commit
+        .as_object()
+        .describe(DescribeOptions::new().describe_tags())
+        .and_then(|d| d.format(None))
+        .ok();
+    Some(Head::Detached { short_sha, describe })
+}
+
+/// Default branch of a repository, the one the HEAD of a remote points to (`origin` first),
+/// otherwise `main` or `master` when the branch exists
+pub fn get_default_branch(repository: &Option<Repository>) -> Option<String> {
+    let r = repository.as_ref()?;
+    let mut remotes: Vec<String> = r.remotes().ok()?.iter().flatten().map(|n| n.to_string()).collect();
+    remotes.sort_by_key(|n| n != "origin");
+
+    let remote_head = remotes.iter().find_map(|remote| {
+        let reference = r.find_reference(&format!("refs/remotes/{}/HEAD", remote)).ok()?;
+        let target = reference.symbolic_target()?;
+        target
+            .strip_prefix(&format!("refs/remotes/{}/", remote))
+            .map(|b| b.to_string())
+    });
+
+    remote_head.or_else(|| {
+        ["main", "master"]
+            .iter()
+            .find(|b| r.find_branch(b, BranchType::Local).is_ok())
+            .map(|b| b.to_string())
+    })
+}
+
+/// HEAD commit of a repository, shown next to it and used to sort by recency
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LastCommit {
+    /// Commit time in seconds since the epoch
+    pub time: i64,
+    pub author: String,
+    pub summary: String,
+}
+
+impl LastCommit {
+    /// Age of the commit relative to `now`, like `3d ago`
+    pub fn age(&self, now: i64) -> String {
+        let seconds = (now - self.time).max(0);
+        match seconds {
+            s if s < 60 => String::from("just now"),
+            s if s < 3600 => format!("{}m ago", s / 60),
+            s if s < 86400 => format!("{}h ago", s / 3600),
+            s if s < 86400 * 30 => format!("{}d ago", s / 86400),
+            s if s < 86400 * 365 => format!("{}mo ago", s / (86400 * 30)),
+            s => format!("{}y ago", s / (86400 * 365)),
+        }
+    }
+}
+
+pub fn get_last_commit(repository: &Option<Repository>) -> Option<LastCommit> {
+    let commit = repository.as_ref()?.head().ok()?.peel_to_commit().ok()?;
+    let author = commit.author().name().unwrap_or_default().to_string();
+    Some(LastCommit {
+        time: commit.time().seconds(),
+        author,
+        summary: commit.summary().unwrap_or_default().to_string(),
+    })
+}
+
+/// Commits the active branch is ahead and behind its upstream
+pub fn get_ahead_behind(repository: &Option<Repository>) -> Option<(usize, usize)> {
+    let r = repository.as_ref()?;
+    let head = r.head().ok()?;
+    if !head.is_branch() {
+        return None;
     }
-    branch_id
+
+    let upstream = Branch::wrap(head).upstream().ok()?;
+    let local = r.head().ok()?.target()?;
+    let remote = upstream.get().target()?;
+    r.graph_ahead_behind(local, remote).ok()
 }
 
-pub fn get_files_changed(repository: &Option<Repository>) -> Option<usize> {
+/// Paths of the linked worktrees of a repository, the main working tree is not included
+pub fn get_repository_worktrees(repository: &Option<Repository>) -> Vec<PathBuf> {
+    let mut worktrees = Vec::new();
     if let Some(r) = repository {
-        return match r.diff_index_to_workdir(None, None) {
-            Ok(diff) => Some(diff.stats().unwrap().files_changed()),
-            Err(_e) => None,
-        };
-    } else {
-        None
+        if let Ok(names) = r.worktrees() {
+            names.iter().flatten().for_each(|name| {
+                if let Ok(worktree) = r.find_worktree(name) {
+                    if worktree.validate().is_ok() {
+                        worktrees.push(worktree.path().to_path_buf());
+                    }
+                }
+            });
+        }
+    }
+    worktrees
+}
+
+/// Working tree, other than the one of `repository`, whose HEAD is on `reference`. Git doesn't
+/// check a branch out in two working trees at once.
+pub fn find_other_checkout(repository: &Repository, reference: &str) -> Option<PathBuf> {
+    // Linked worktrees point to the git folder of their main repository in `commondir`
+    let common_dir = fs::read_to_string(repository.path().join("commondir"))
+        .ok()
+        .map(|c| repository.path().join(c.trim()))
+        .unwrap_or_else(|| repository.path().to_path_buf());
+    let main = Repository::open(common_dir).ok()?;
+    let mut checkouts: Vec<Repository> = main
+        .worktrees()
+        .map(|names| {
+            names
+                .iter()
+                .flatten()
+                .filter_map(|name| main.find_worktree(name).ok())
+                .filter_map(|w| Repository::open_from_worktree(&w).ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    if !main.is_bare() {
+        checkouts.push(main);
+    }
+
+    let same = |a: &Path, b: &Path| fs::canonicalize(a).ok() == fs::canonicalize(b).ok();
+    checkouts
+        .into_iter()
+        .filter(|r| !same(r.path(), repository.path()))
+        .find(|r| {
+            r.find_reference("HEAD")
+                .is_ok_and(|h| h.symbolic_target() == Some(reference))
+        })
+        .and_then(|r| r.workdir().map(|w| w.to_path_buf()))
+}
+
+/// Initialises a repository in `path`, optionally adding a remote and committing every file
+pub fn init_repository(
+    path: &PathBuf,
+    remote: Option<(&str, &str)>,
+    initial_commit: bool,
+) -> Result<String, git2::Error> {
+    let repo = Repository::init(path)?;
+
+    if let Some((name, url)) = remote {
+        repo.remote(name, url)?;
+    }
+
+    if initial_commit {
+        let mut index = repo.index()?;
+        index.add_all(["*"].iter(), IndexAddOption::DEFAULT, None)?;
+        index.write()?;
+        let tree = repo.find_tree(index.write_tree()?)?;
+        let sig = repo.signature()?;
+        create_commit(&repo, &sig, &sig, "Initial commit", &tree, &[])?;
+    }
+
+    Ok(String::from("Repository is initialized!"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git_operations::test_utils::{commit_all, init_repository, write_file};
+
+    fn head(repository: &Repository) -> Option<Head> {
+        get_repository_head(&Repository::open(repository.path()).ok())
+    }
+
+    #[test]
+    fn unborn_branch_is_named() {
+        let (_dir, repository) = init_repository();
+        repository.set_head("refs/heads/trunk").unwrap();
+        assert_eq!(head(&repository), Some(Head::Unborn(String::from("trunk"))));
+        assert_eq!(head(&repository).unwrap().to_string(), "trunk (no commits)");
+        assert_eq!(head(&repository).unwrap().branch_name(), Some("trunk"));
+    }
+
+    #[test]
+    fn branch_is_named() {
+        let (_dir, repository) = init_repository();
+        write_file(&repository, "a.txt", "a\n");
+        commit_all(&repository, "init");
+        let commit = repository.head().unwrap().peel_to_commit().unwrap();
+        repository.branch("feature/x", &commit, false).unwrap();
+        repository.set_head("refs/heads/feature/x").unwrap();
+        assert_eq!(head(&repository), Some(Head::Branch(String::from("feature/x"))));
+    }
+
+    #[test]
+    fn detached_head_is_described_by_the_closest_tag() {
+        let (_dir, repository) = init_repository();
+        write_file(&repository, "a.txt", "a\n");
+        commit_all(&repository, "init");
+        let tagged = repository.head().unwrap().peel_to_commit().unwrap();
+        let sig = repository.signature().unwrap();
+        repository.tag("v1.0", tagged.as_object(), &sig, "Release", false).unwrap();
+        repository.set_head_detached(tagged.id()).unwrap();
+        assert_eq!(head(&repository).unwrap().to_string(), "detached v1.0");
+        assert_eq!(head(&repository).unwrap().branch_name(), None);
+
+        write_file(&repository, "b.txt", "b\n");
+        commit_all(&repository, "second");
+        let short_sha = repository.head().unwrap().peel_to_commit().unwrap().as_object().short_id().unwrap();
+        let short_sha = short_sha.as_str().unwrap().to_string();
+        assert_eq!(
+            head(&repository).unwrap().to_string(),
+            format!("detached v1.0-1-g{}", short_sha)
+        );
+    }
+
+    #[test]
+    fn detached_head_without_tag_is_its_short_id() {
+        let (_dir, repository) = init_repository();
+        write_file(&repository, "a.txt", "a\n");
+        commit_all(&repository, "init");
+        let commit = repository.head().unwrap().peel_to_commit().unwrap();
+        repository.set_head_detached(commit.id()).unwrap();
+        let short_sha = commit.as_object().short_id().unwrap().as_str().unwrap().to_string();
+        assert_eq!(
+            head(&repository),
+            Some(Head::Detached {
+                short_sha: short_sha.clone(),
+                describe: None
+            })
+        );
+        assert_eq!(head(&repository).unwrap().to_string(), format!("detached {}", short_sha));
+    }
+
+    #[test]
+    fn branch_of_another_worktree_is_found() {
+        let (_dir, repository) = init_repository();
+        write_file(&repository, "a.txt", "a\n");
+        commit_all(&repository, "init");
+        let main_branch = repository.head().unwrap().name().unwrap().to_string();
+        let worktrees = tempfile::TempDir::new().unwrap();
+        let path = worktrees.path().join("feat");
+        let worktree = repository.worktree("feat", &path, None).unwrap();
+        let linked = Repository::open_from_worktree(&worktree).unwrap();
+
+        let found = find_other_checkout(&repository, "refs/heads/feat").unwrap();
+        assert_eq!(fs::canonicalize(found).unwrap(), fs::canonicalize(&path).unwrap());
+        assert!(find_other_checkout(&linked, &main_branch).is_some());
+        assert_eq!(find_other_checkout(&repository, &main_branch), None);
+        assert_eq!(find_other_checkout(&repository, "refs/heads/other"), None);
     }
 }
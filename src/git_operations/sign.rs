@@ -0,0 +1,186 @@
+use crossterm::event::{DisableMouseCapture, EnableMouseCapture};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, is_raw_mode_enabled, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use git2::{Config, Repository, Signature};
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set when a signing program was given the terminal, the TUI has to be drawn again
+static TERMINAL_SUSPENDED: AtomicBool = AtomicBool::new(false);
+
+/// Whether commits or tags are signed, `key` is `commit.gpgsign` or `tag.gpgsign`
+pub fn should_sign(repository: &Repository, key: &str) -> bool {
+    repository
+        .config()
+        .and_then(|c| c.get_bool(key))
+        .unwrap_or(false)
+}
+
+/// Signs a commit or tag buffer with the program `gpg.format` asks for, returning the armored
+/// signature
+pub fn sign_buffer(repository: &Repository, signer: &Signature, buffer: &str) -> Result<String, git2::Error> {
+    let config = repository.config()?.snapshot()?;
+    let key = config.get_string("user.signingkey").ok();
+    match config.get_string("gpg.format").as_deref().unwrap_or("openpgp") {
+        "ssh" => sign_with_ssh(&config, key, buffer),
+        "x509" => {
+            let program = config.get_string("gpg.x509.program").unwrap_or_else(|_| String::from("gpgsm"));
+            sign_with_gpg(&program, key, signer, buffer)
+        }
+        "openpgp" => {
+            let program = config
+                .get_string("gpg.openpgp.program")
+                .or_else(|_| config.get_string("gpg.program"))
+                .unwrap_or_else(|_| String::from("gpg"));
+            sign_with_gpg(&program, key, signer, buffer)
+        }
+        format => Err(git2::Error::from_str(&format!("Unsupported gpg.format {}", format))),
+    }
+}
+
+/// Signs with gpg or gpgsm, the key defaults to the committer like git does
+fn sign_with_gpg(program: &str, key: Option<String>, signer: &Signature, buffer: &str) -> Result<String, git2::Error> {
+    let key = key.unwrap_or_else(|| {
+        format!("{} <{}>", signer.name().unwrap_or_default(), signer.email().unwrap_or_default())
+    });
+    let mut command = Command::new(program);
+    command.args(["--status-fd=2", "-bsau", &key]);
+    let output = run_in_terminal(&mut command, Some(buffer.as_bytes()))
+        .map_err(|e| git2::Error::from_str(&format!("Could not run {}: {}", program, e)))?;
+
+    let signature = String::from_utf8_lossy(&output.stdout).to_string();
+    if !output.status.success() || signature.is_empty() {
+        return Err(signing_failed(&output.stderr));
+    }
+    Ok(signature)
+}
+
+/// Signs with ssh-keygen, `user.signingkey` is either the path of a key or a public key whose
+/// private key is in the ssh-agent
+fn sign_with_ssh(config: &Config, key: Option<String>, buffer: &str) -> Result<String, git2::Error> {
+    let program = config.get_string("gpg.ssh.program").unwrap_or_else(|_| String::from("ssh-keygen"));
+    let key = key.ok_or_else(|| git2::Error::from_str("user.signingkey must be set to sign with ssh"))?;
+    // The buffer and the public key go in a new folder, other users can't put links in it
+    let temp = tempfile::Builder::new()
+        .prefix("gitten-sign")
+        .tempdir()
+        .map_err(|e| git2::Error::from_str(&e.to_string()))?;
+    let buffer_file = temp.path().join("buffer");
+    let to_io = |e: io::Error| git2::Error::from_str(&e.to_string());
+
+    let literal = key
+        .strip_prefix("key::")
+        .map(|k| k.to_string())
+        .or_else(|| Some(key.clone()).filter(|k| k.starts_with("ssh-")));
+    let mut args = vec![String::from("-Y"), String::from("sign"), String::from("-n"), String::from("git")];
+    let key_file = match &literal {
+        Some(public_key) => {
+            let path = temp.path().join("key.pub");
+            fs::write(&path, public_key).map_err(to_io)?;
+            args.push(String::from("-U"));
+            path
+        }
+        None => expand_home(&key),
+    };
+    args.push(String::from("-f"));
+    args.push(key_file.to_string_lossy().to_string());
+    args.push(buffer_file.to_string_lossy().to_string());
+    fs::write(&buffer_file, buffer).map_err(to_io)?;
+
+    let output = run_in_terminal(Command::new(&program).args(&args), None)
+        .map_err(|e| git2::Error::from_str(&format!("Could not run {}: {}", program, e)))?;
+    match fs::read_to_string(buffer_file.with_extension("sig")) {
+        Ok(signature) if output.status.success() => Ok(signature),
+        _ => Err(signing_failed(&output.stderr)),
+    }
+}
+
+/// Runs a signing program with the terminal given back to it while the TUI is running, so it
+/// can ask for a passphrase. Its stdout and stderr are captured, stdin is given `input`.
+fn run_in_terminal(command: &mut Command, input: Option<&[u8]>) -> io::Result<Output> {
+    let suspend = is_raw_mode_enabled().unwrap_or(false);
+    if suspend {
+        disable_raw_mode()?;
+        execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture)?;
+        TERMINAL_SUSPENDED.store(true, Ordering::SeqCst);
+    }
+
+    let output = command
+        .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .and_then(|mut child| {
+            if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+                stdin.write_all(input)?;
+            }
+            child.wait_with_output()
+        });
+
+    if suspend {
+        enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)?;
+    }
+    output
+}
+
+/// Whether a signing program was given the terminal since the last call
+pub fn terminal_was_suspended() -> bool {
+    TERMINAL_SUSPENDED.swap(false, Ordering::SeqCst)
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+fn signing_failed(stderr: &[u8]) -> git2::Error {
+    let mut message = String::from("Signing failed");
+    String::from_utf8_lossy(stderr)
+        .lines()
+        .filter(|l| !l.trim().is_empty() && !l.starts_with("[GNUPG:]"))
+        .for_each(|l| message.push_str(&format!("\n{}", l)));
+    git2::Error::from_str(&message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git_operations::test_utils::init_repository;
+
+    #[cfg(unix)]
+    #[test]
+    fn buffer_is_signed_with_ssh_in_its_own_folder() {
+        use std::os::unix::fs::PermissionsExt;
+        let (dir, repository) = init_repository();
+        // Stands in for ssh-keygen: checks the files are in their own folder, then signs the last argument
+        let program = dir.path().join("ssh-keygen.sh");
+        fs::write(
+            &program,
+            "#!/bin/sh\n\
+             for last; do :; done\n\
+             folder=$(dirname \"$last\")\n\
+             case \"$folder\" in */gitten-sign*) ;; *) exit 1 ;; esac\n\
+             grep -q '^ssh-ed25519 ' \"$folder/key.pub\" || exit 2\n\
+             printf 'SSH SIGNATURE of %s\\n' \"$(cat \"$last\")\" > \"$last.sig\"\n",
+        )
+        .unwrap();
+        fs::set_permissions(&program, fs::Permissions::from_mode(0o755)).unwrap();
+        let mut config = repository.config().unwrap();
+        config.set_str("gpg.format", "ssh").unwrap();
+        config.set_str("gpg.ssh.program", program.to_str().unwrap()).unwrap();
+        config.set_str("user.signingkey", "ssh-ed25519 AAAA jane@example.com").unwrap();
+
+        let signer = repository.signature().unwrap();
+        let signature = sign_buffer(&repository, &signer, "buffer").unwrap();
+        assert_eq!(signature, "SSH SIGNATURE of buffer\n");
+    }
+}
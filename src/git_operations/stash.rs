@@ -0,0 +1,77 @@
+use git2::{DiffFormat, Repository, StashApplyOptions};
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct StashEntry {
+    pub index: usize,
+    pub message: String,
+}
+
+impl Display for StashEntry {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "stash@{{{}}}: {}", self.index, self.message)
+    }
+}
+
+pub fn get_repository_stashes(repository: &mut Option<Repository>) -> Vec<StashEntry> {
+    let mut stashes = Vec::new();
+    if let Some(r) = repository {
+        let _ = r.stash_foreach(|index, message, _oid| {
+            stashes.push(StashEntry {
+                index,
+                message: message.to_string(),
+            });
+            true
+        });
+    }
+    stashes
+}
+
+/// Number of stash entries, read from the reflog of the stash reference
+pub fn get_stash_count(repository: &Option<Repository>) -> usize {
+    match repository {
+        Some(r) => r.reflog("refs/stash").map(|l| l.len()).unwrap_or(0),
+        None => 0,
+    }
+}
+
+pub fn save_stash(repository: &mut Repository, message: Option<&str>) -> Result<String, git2::Error> {
+    let sig = repository.signature()?;
+    repository.stash_save(&sig, message.unwrap_or("WIP on gitten"), None)?;
+    Ok(String::from("Changes are stashed!"))
+}
+
+pub fn pop_stash(repository: &mut Repository, index: usize) -> Result<String, git2::Error> {
+    repository.stash_pop(index, Some(&mut StashApplyOptions::new()))?;
+    Ok(format!("stash@{{{}}} is popped!", index))
+}
+
+pub fn apply_stash(repository: &mut Repository, index: usize) -> Result<String, git2::Error> {
+    repository.stash_apply(index, Some(&mut StashApplyOptions::new()))?;
+    Ok(format!("stash@{{{}}} is applied!", index))
+}
+
+pub fn drop_stash(repository: &mut Repository, index: usize) -> Result<String, git2::Error> {
+    repository.stash_drop(index)?;
+    Ok(format!("stash@{{{}}} is dropped!", index))
+}
+
+/// Patch of a stash entry against the commit it was made on
+pub fn print_stash_diff(repository: &Repository, index: usize) -> Result<String, git2::Error> {
+    let stash = repository
+        .revparse_single(&format!("stash@{{{}}}", index))?
+        .peel_to_commit()?;
+    let base = stash.parent(0)?;
+    let diff = repository.diff_tree_to_tree(Some(&base.tree()?), Some(&stash.tree()?), None)?;
+
+    let mut patch = String::new();
+    diff.print(DiffFormat::Patch, |_delta, _hunk, line| {
+        if let '+' | '-' | ' ' = line.origin() {
+            patch.push(line.origin());
+        }
+        patch.push_str(&String::from_utf8_lossy(line.content()));
+        true
+    })?;
+    Ok(patch)
+}
@@ -0,0 +1,338 @@
+use crate::git_operations::commit::{create_commit, prepare_tree, run_commit_msg_hook};
+use crate::git_operations::hooks::run_post_hook;
+use git2::{Oid, Repository, RepositoryState, Signature};
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Operation left in progress in a repository, with the step reached for rebases
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OperationState {
+    pub state: RepositoryState,
+    pub progress: Option<(usize, usize)>,
+}
+
+impl Display for OperationState {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let name = match self.state {
+            RepositoryState::Merge => "MERGING",
+            RepositoryState::Revert | RepositoryState::RevertSequence => "REVERTING",
+            RepositoryState::CherryPick | RepositoryState::CherryPickSequence => "CHERRY-PICKING",
+            RepositoryState::Bisect => "BISECTING",
+            RepositoryState::Rebase | RepositoryState::RebaseInteractive | RepositoryState::RebaseMerge => {
+                "REBASING"
+            }
+            RepositoryState::ApplyMailbox | RepositoryState::ApplyMailboxOrRebase => "AM",
+            RepositoryState::Clean => "",
+        };
+        match self.progress {
+            Some((step, total)) => write!(f, "{} {}/{}", name, step, total),
+            None => write!(f, "{}", name),
+        }
+    }
+}
+
+fn is_rebase(state: RepositoryState) -> bool {
+    matches!(
+        state,
+        RepositoryState::Rebase
+            | RepositoryState::RebaseInteractive
+            | RepositoryState::RebaseMerge
+            | RepositoryState::ApplyMailboxOrRebase
+    )
+}
+
+/// Whether the operation is a `git am`, which shares the rebase-apply folder with `git rebase`
+/// and leaves a file of its own in it
+fn is_am(repository: &Repository, state: RepositoryState) -> bool {
+    state == RepositoryState::ApplyMailbox
+        || (state == RepositoryState::ApplyMailboxOrRebase && repository.path().join("rebase-apply/applying").exists())
+}
+
+fn operation_name(state: RepositoryState) -> &'static str {
+    match state {
+        RepositoryState::Merge => "Merge",
+        RepositoryState::Revert | RepositoryState::RevertSequence => "Revert",
+        RepositoryState::CherryPick | RepositoryState::CherryPickSequence => "Cherry-pick",
+        RepositoryState::Bisect => "Bisect",
+        RepositoryState::ApplyMailbox => "Am",
+        s if is_rebase(s) => "Rebase",
+        _ => "Operation",
+    }
+}
+
+fn read_state_file(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|c| c.trim().to_string())
+}
+
+/// Folder git keeps the state of a rebase in, depending on the backend used
+fn rebase_folder(repository: &Repository) -> Option<std::path::PathBuf> {
+    ["rebase-merge", "rebase-apply"]
+        .iter()
+        .map(|f| repository.path().join(f))
+        .find(|p| p.is_dir())
+}
+
+/// Current and total steps of a rebase, read from the files git writes while rebasing
+fn rebase_progress(repository: &Repository) -> Option<(usize, usize)> {
+    let folder = rebase_folder(repository)?;
+    let (step, total) = if folder.ends_with("rebase-merge") {
+        ("msgnum", "end")
+    } else {
+        ("next", "last")
+    };
+    let step = read_state_file(&folder.join(step))?.parse().ok()?;
+    let total = read_state_file(&folder.join(total))?.parse().ok()?;
+    Some((step, total))
+}
+
+pub fn get_operation_state(repository: &Option<Repository>) -> Option<OperationState> {
+    let r = repository.as_ref()?;
+    let state = r.state();
+    if state == RepositoryState::Clean {
+        return None;
+    }
+
+    let progress = if is_rebase(state) { rebase_progress(r) } else { None };
+    Some(OperationState { state, progress })
+}
+
+/// Aborts the operation in progress through git, like `git merge --abort`, so local changes made
+/// before the operation are kept the way git keeps them
+pub fn abort_operation(repository: &Repository) -> Result<String, git2::Error> {
+    let state = repository.state();
+    let args: &[&str] = match state {
+        RepositoryState::Clean => return Err(git2::Error::from_str("No operation in progress")),
+        RepositoryState::Merge => &["merge", "--abort"],
+        RepositoryState::Revert | RepositoryState::RevertSequence => &["revert", "--abort"],
+        RepositoryState::CherryPick | RepositoryState::CherryPickSequence => &["cherry-pick", "--abort"],
+        RepositoryState::Bisect => {
+            run_git(repository, &["bisect", "reset"])?;
+            return Ok(String::from("Bisect is reset!"));
+        }
+        s if is_am(repository, s) => &["am", "--abort"],
+        _ => &["rebase", "--abort"],
+    };
+    run_git(repository, args)?;
+    Ok(format!("{} is aborted!", if is_am(repository, state) { "Am" } else { operation_name(state) }))
+}
+
+/// Runs a git command in the working tree, its output is returned in the error when it fails
+fn run_git(repository: &Repository, args: &[&str]) -> Result<(), git2::Error> {
+    let workdir = repository
+        .workdir()
+        .ok_or_else(|| git2::Error::from_str("Operation needs a working tree"))?;
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(workdir)
+        // Messages git would ask for are taken as they are
+        .env("GIT_EDITOR", "true")
+        .output()
+        .map_err(|e| git2::Error::from_str(&format!("Could not run git: {}", e)))?;
+    if output.status.success() {
+        return Ok(());
+    }
+
+    let mut message = format!("git {} failed", args.join(" "));
+    [&output.stdout, &output.stderr]
+        .iter()
+        .flat_map(|o| String::from_utf8_lossy(o).lines().map(|l| l.to_string()).collect::<Vec<String>>())
+        .filter(|l| !l.trim().is_empty())
+        .for_each(|l| message.push_str(&format!("\n{}", l)));
+    Err(git2::Error::from_str(&message))
+}
+
+/// Continues the operation in progress once its conflicts are resolved in the index
+pub fn continue_operation(repository: &mut Repository) -> Result<String, git2::Error> {
+    let state = repository.state();
+    if repository.index()?.has_conflicts() {
+        return Err(git2::Error::from_str("Resolve the conflicts and stage them first"));
+    }
+
+    match state {
+        RepositoryState::Clean => Err(git2::Error::from_str("No operation in progress")),
+        RepositoryState::Merge => {
+            let mut merge_heads = Vec::new();
+            repository.mergehead_foreach(|oid| {
+                merge_heads.push(*oid);
+                true
+            })?;
+            let mut parents = vec![repository.head()?.peel_to_commit()?];
+            for oid in merge_heads {
+                parents.push(repository.find_commit(oid)?);
+            }
+            let sig = repository.signature()?;
+            let note = commit_index(repository, &sig, &sig, parents.iter().collect())?;
+            Ok(format!("Merge is concluded!{}", note))
+        }
+        RepositoryState::CherryPick | RepositoryState::Revert => {
+            let head_file = if state == RepositoryState::CherryPick { "CHERRY_PICK_HEAD" } else { "REVERT_HEAD" };
+            let picked = read_state_file(&repository.path().join(head_file))
+                .ok_or_else(|| git2::Error::from_str("Operation has no commit"))?;
+            let picked = repository.find_commit(Oid::from_str(&picked)?)?;
+            let committer = repository.signature()?;
+            // A cherry-pick keeps the author of the picked commit, a revert is authored by the committer
+            let author = if state == RepositoryState::CherryPick { picked.author() } else { committer.clone() };
+            let head = repository.head()?.peel_to_commit()?;
+            let note = commit_index(repository, &author, &committer, vec![&head])?;
+            Ok(format!("{} is concluded!{}", operation_name(state), note))
+        }
+        s if is_am(repository, s) => continue_with_git(repository, "am", "Am"),
+        RepositoryState::CherryPickSequence => continue_with_git(repository, "cherry-pick", "Cherry-pick"),
+        RepositoryState::RevertSequence => continue_with_git(repository, "revert", "Revert"),
+        s if is_rebase(s) => continue_with_git(repository, "rebase", "Rebase"),
+        RepositoryState::Bisect => Err(git2::Error::from_str("A bisect can't be continued, use :abort to reset it")),
+        _ => Err(git2::Error::from_str("This operation must be continued with git")),
+    }
+}
+
+/// Commits the index on HEAD with the message git prepared for the operation, returning the
+/// failure of the post-commit hook if any
+fn commit_index(
+    repository: &Repository,
+    author: &Signature,
+    committer: &Signature,
+    parents: Vec<&git2::Commit>,
+) -> Result<String, git2::Error> {
+    let tree = prepare_tree(repository)?;
+    // The prepared message lists the conflicts as comments, they are stripped like git does
+    let message = git2::message_prettify(repository.message().unwrap_or_default(), Some(b'#'))?;
+    let message = run_commit_msg_hook(repository, &message)?;
+    create_commit(repository, author, committer, &message, &tree, &parents)?;
+    repository.cleanup_state()?;
+    Ok(run_post_hook(repository, "post-commit", &[]))
+}
+
+/// Continues an operation of several steps through git, which started it: a rebase, an am, or a
+/// cherry-pick or revert of several commits
+fn continue_with_git(repository: &Repository, command: &str, name: &str) -> Result<String, git2::Error> {
+    match run_git(repository, &[command, "--continue"]) {
+        Ok(()) if repository.state() != RepositoryState::Clean => Ok(format!("{} is continued!", name)),
+        Ok(()) => Ok(format!("{} is concluded!", name)),
+        // The next step stopping on conflicts makes git fail, the operation goes on once resolved
+        Err(_) if repository.index()?.has_conflicts() => Ok(format!("{} stopped on conflicts!", name)),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git_operations::test_utils::{commit_all, init_repository, write_file};
+
+    fn git(repository: &Repository, args: &[&str]) -> bool {
+        Command::new("git")
+            .args(args)
+            .current_dir(repository.workdir().unwrap())
+            .output()
+            .unwrap()
+            .status
+            .success()
+    }
+
+    /// Repository with a branch `other` conflicting with the current one on `a.txt`
+    fn repository_with_conflict() -> (tempfile::TempDir, Repository) {
+        let (dir, repository) = init_repository();
+        write_file(&repository, "a.txt", "base\n");
+        write_file(&repository, "b.txt", "b\n");
+        commit_all(&repository, "base");
+        assert!(git(&repository, &["branch", "other"]));
+        write_file(&repository, "a.txt", "ours\n");
+        commit_all(&repository, "ours");
+        assert!(git(&repository, &["checkout", "-q", "other"]));
+        write_file(&repository, "a.txt", "theirs\n");
+        commit_all(&repository, "theirs");
+        assert!(git(&repository, &["checkout", "-q", "-"]));
+        (dir, repository)
+    }
+
+    #[test]
+    fn merge_abort_keeps_local_changes() {
+        let (_dir, repository) = repository_with_conflict();
+        let head = repository.head().unwrap().target();
+        // A change made before the merge, in a file the merge doesn't touch
+        write_file(&repository, "b.txt", "local\n");
+        assert!(!git(&repository, &["merge", "other"]));
+        assert_eq!(repository.state(), RepositoryState::Merge);
+
+        assert_eq!(abort_operation(&repository).unwrap(), "Merge is aborted!");
+        assert_eq!(repository.state(), RepositoryState::Clean);
+        assert_eq!(repository.head().unwrap().target(), head);
+        let local = std::fs::read_to_string(repository.workdir().unwrap().join("b.txt")).unwrap();
+        assert_eq!(local, "local\n");
+    }
+
+    #[test]
+    fn rebase_is_continued_and_aborted_through_git() {
+        let (_dir, repository) = repository_with_conflict();
+        let head = repository.head().unwrap().target();
+        assert!(!git(&repository, &["rebase", "other"]));
+        assert!(is_rebase(repository.state()));
+        assert_eq!(operation_name(repository.state()), "Rebase");
+
+        let mut repository = repository;
+        assert!(continue_operation(&mut repository).is_err());
+        assert_eq!(abort_operation(&repository).unwrap(), "Rebase is aborted!");
+        assert_eq!(repository.state(), RepositoryState::Clean);
+        assert_eq!(repository.head().unwrap().target(), head);
+
+        assert!(!git(&repository, &["rebase", "other"]));
+        write_file(&repository, "a.txt", "resolved\n");
+        assert!(git(&repository, &["add", "a.txt"]));
+        assert_eq!(continue_operation(&mut repository).unwrap(), "Rebase is concluded!");
+        assert_eq!(repository.state(), RepositoryState::Clean);
+    }
+
+    #[test]
+    fn cherry_pick_of_several_commits_is_continued_through_git() {
+        let (_dir, repository) = repository_with_conflict();
+        assert!(git(&repository, &["checkout", "-q", "other"]));
+        write_file(&repository, "c.txt", "c\n");
+        commit_all(&repository, "after theirs");
+        assert!(git(&repository, &["checkout", "-q", "-"]));
+        assert!(!git(&repository, &["cherry-pick", "HEAD..other"]));
+        assert_eq!(repository.state(), RepositoryState::CherryPickSequence);
+
+        let mut repository = repository;
+        write_file(&repository, "a.txt", "resolved\n");
+        assert!(git(&repository, &["add", "a.txt"]));
+        assert_eq!(continue_operation(&mut repository).unwrap(), "Cherry-pick is concluded!");
+        assert_eq!(repository.state(), RepositoryState::Clean);
+        assert!(repository.workdir().unwrap().join("c.txt").exists());
+    }
+
+    #[test]
+    fn am_is_continued_and_aborted_as_an_am() {
+        let (dir, repository) = repository_with_conflict();
+        let patch = dir.path().join("theirs.patch");
+        let output = Command::new("git")
+            .args(["format-patch", "-1", "--stdout", "other"])
+            .current_dir(repository.workdir().unwrap())
+            .output()
+            .unwrap();
+        fs::write(&patch, output.stdout).unwrap();
+        let head = repository.head().unwrap().target();
+
+        assert!(!git(&repository, &["am", "-3", patch.to_str().unwrap()]));
+        assert!(is_am(&repository, repository.state()));
+        assert_eq!(abort_operation(&repository).unwrap(), "Am is aborted!");
+        assert_eq!(repository.head().unwrap().target(), head);
+
+        assert!(!git(&repository, &["am", "-3", patch.to_str().unwrap()]));
+        let mut repository = repository;
+        write_file(&repository, "a.txt", "resolved\n");
+        assert!(git(&repository, &["add", "a.txt"]));
+        assert_eq!(continue_operation(&mut repository).unwrap(), "Am is concluded!");
+        assert_eq!(repository.state(), RepositoryState::Clean);
+        assert_ne!(repository.head().unwrap().target(), head);
+    }
+
+    #[test]
+    fn operations_are_named() {
+        assert_eq!(operation_name(RepositoryState::ApplyMailbox), "Am");
+        assert_eq!(operation_name(RepositoryState::CherryPickSequence), "Cherry-pick");
+        assert_eq!(operation_name(RepositoryState::RebaseInteractive), "Rebase");
+    }
+}
@@ -0,0 +1,310 @@
+use git2::{DiffDelta, Repository, Status, StatusOptions};
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+/// Part of the working tree status a file is listed in
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum StatusSection {
+    Conflicted,
+    Staged,
+    #[default]
+    Unstaged,
+    Untracked,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum StatusChange {
+    New,
+    #[default]
+    Modified,
+    Deleted,
+    Renamed,
+    Typechange,
+    Conflicted,
+}
+
+/// A file of the working tree status, a file with staged and unstaged changes is listed twice
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FileStatus {
+    pub path: String,
+    /// Path before the rename for renamed files
+    pub old_path: Option<String>,
+    pub section: StatusSection,
+    pub change: StatusChange,
+}
+
+impl Display for FileStatus {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match &self.old_path {
+            Some(old_path) => write!(f, "{} -> {}", old_path, self.path),
+            None => write!(f, "{}", self.path),
+        }
+    }
+}
+
+impl Display for StatusSection {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                StatusSection::Conflicted => "conflicted",
+                StatusSection::Staged => "staged",
+                StatusSection::Unstaged => "unstaged",
+                StatusSection::Untracked => "untracked",
+            }
+        )
+    }
+}
+
+impl Display for StatusChange {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                StatusChange::New => "new",
+                StatusChange::Modified => "modified",
+                StatusChange::Deleted => "deleted",
+                StatusChange::Renamed => "renamed",
+                StatusChange::Typechange => "typechange",
+                StatusChange::Conflicted => "both modified",
+            }
+        )
+    }
+}
+
+fn index_change(status: Status) -> Option<StatusChange> {
+    if status.is_index_new() {
+        Some(StatusChange::New)
+    } else if status.is_index_modified() {
+        Some(StatusChange::Modified)
+    } else if status.is_index_deleted() {
+        Some(StatusChange::Deleted)
+    } else if status.is_index_renamed() {
+        Some(StatusChange::Renamed)
+    } else if status.is_index_typechange() {
+        Some(StatusChange::Typechange)
+    } else {
+        None
+    }
+}
+
+fn workdir_change(status: Status) -> Option<StatusChange> {
+    if status.is_wt_modified() {
+        Some(StatusChange::Modified)
+    } else if status.is_wt_deleted() {
+        Some(StatusChange::Deleted)
+    } else if status.is_wt_renamed() {
+        Some(StatusChange::Renamed)
+    } else if status.is_wt_typechange() {
+        Some(StatusChange::Typechange)
+    } else {
+        None
+    }
+}
+
+/// Number of files in each section of the working tree status
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StatusCounts {
+    pub conflicted: usize,
+    pub staged: usize,
+    pub modified: usize,
+    pub untracked: usize,
+}
+
+impl StatusCounts {
+    pub fn from_files(files: &[FileStatus]) -> StatusCounts {
+        let mut counts = StatusCounts::default();
+        files.iter().for_each(|f| match f.section {
+            StatusSection::Conflicted => counts.conflicted += 1,
+            StatusSection::Staged => counts.staged += 1,
+            StatusSection::Unstaged => counts.modified += 1,
+            StatusSection::Untracked => counts.untracked += 1,
+        });
+        counts
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        *self != StatusCounts::default()
+    }
+}
+
+impl Display for StatusCounts {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let counts: Vec<String> = [
+            ("!", self.conflicted),
+            ("+", self.staged),
+            ("~", self.modified),
+            ("?", self.untracked),
+        ]
+        .iter()
+        .filter(|(_, count)| *count > 0)
+        .map(|(sign, count)| format!("{}{}", sign, count))
+        .collect();
+        write!(f, "{}", counts.join(" "))
+    }
+}
+
+/// New path of a delta, and its old path when the file is renamed
+fn delta_paths(delta: Option<DiffDelta>, change: StatusChange, path: &str) -> (String, Option<String>) {
+    let new_path = delta
+        .as_ref()
+        .and_then(|d| d.new_file().path())
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string());
+    let old_path = delta
+        .filter(|_| change == StatusChange::Renamed)
+        .and_then(|d| d.old_file().path().map(|p| p.to_string_lossy().to_string()));
+    (new_path, old_path)
+}
+
+/// Reads the full working tree status of a repository, sorted by section and path
+pub fn get_repository_status(repository: &Option<Repository>) -> Vec<FileStatus> {
+    let mut files = Vec::new();
+    let r = match repository {
+        Some(r) if !r.is_bare() => r,
+        _ => return files,
+    };
+
+    let mut opts = StatusOptions::new();
+    opts.include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .renames_head_to_index(true)
+        .renames_index_to_workdir(true);
+
+    let statuses = match r.statuses(Some(&mut opts)) {
+        Ok(s) => s,
+        Err(_) => return files,
+    };
+
+    statuses.iter().for_each(|entry| {
+        let status = entry.status();
+        let path = entry.path().unwrap_or_default().to_string();
+
+        if status.is_conflicted() {
+            files.push(FileStatus {
+                path,
+                old_path: None,
+                section: StatusSection::Conflicted,
+                change: StatusChange::Conflicted,
+            });
+            return;
+        }
+
+        if let Some(change) = index_change(status) {
+            let (new_path, old_path) = delta_paths(entry.head_to_index(), change, &path);
+            files.push(FileStatus {
+                path: new_path,
+                old_path,
+                section: StatusSection::Staged,
+                change,
+            });
+        }
+
+        if let Some(change) = workdir_change(status) {
+            let (new_path, old_path) = delta_paths(entry.index_to_workdir(), change, &path);
+            files.push(FileStatus {
+                path: new_path,
+                old_path,
+                section: StatusSection::Unstaged,
+                change,
+            });
+        }
+
+        if status.is_wt_new() {
+            files.push(FileStatus {
+                path,
+                old_path: None,
+                section: StatusSection::Untracked,
+                change: StatusChange::New,
+            });
+        }
+    });
+
+    files.sort_by(|a, b| (a.section, &a.path).cmp(&(b.section, &b.path)));
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git_operations::test_utils::{commit_all, init_repository, write_file};
+    use std::fs;
+    use std::path::Path;
+
+    fn file(path: &str, old_path: Option<&str>, section: StatusSection, change: StatusChange) -> FileStatus {
+        FileStatus {
+            path: path.to_string(),
+            old_path: old_path.map(|p| p.to_string()),
+            section,
+            change,
+        }
+    }
+
+    #[test]
+    fn status_is_read_by_section() {
+        let (_dir, repository) = init_repository();
+        write_file(&repository, "a.txt", "a\n");
+        write_file(&repository, "b.txt", "a file long enough to be found as renamed\n");
+        write_file(&repository, "c.txt", "c\n");
+        commit_all(&repository, "init");
+
+        write_file(&repository, "a.txt", "staged\n");
+        let workdir = repository.workdir().unwrap();
+        fs::rename(workdir.join("b.txt"), workdir.join("renamed.txt")).unwrap();
+        let mut index = repository.index().unwrap();
+        index.add_path(Path::new("a.txt")).unwrap();
+        index.remove_path(Path::new("b.txt")).unwrap();
+        index.add_path(Path::new("renamed.txt")).unwrap();
+        index.write().unwrap();
+        write_file(&repository, "a.txt", "unstaged\n");
+        fs::remove_file(workdir.join("c.txt")).unwrap();
+        write_file(&repository, "folder/new.txt", "new\n");
+
+        let files = get_repository_status(&Some(repository));
+        assert_eq!(
+            files,
+            [
+                file("a.txt", None, StatusSection::Staged, StatusChange::Modified),
+                file("renamed.txt", Some("b.txt"), StatusSection::Staged, StatusChange::Renamed),
+                file("a.txt", None, StatusSection::Unstaged, StatusChange::Modified),
+                file("c.txt", None, StatusSection::Unstaged, StatusChange::Deleted),
+                file("folder/new.txt", None, StatusSection::Untracked, StatusChange::New),
+            ]
+        );
+
+        let counts = StatusCounts::from_files(&files);
+        assert_eq!(
+            counts,
+            StatusCounts {
+                conflicted: 0,
+                staged: 2,
+                modified: 2,
+                untracked: 1
+            }
+        );
+        assert!(counts.is_dirty());
+        assert_eq!(counts.to_string(), "+2 ~2 ?1");
+    }
+
+    #[test]
+    fn clean_repository_has_no_counts() {
+        let (_dir, repository) = init_repository();
+        write_file(&repository, "a.txt", "a\n");
+        commit_all(&repository, "init");
+        let counts = StatusCounts::from_files(&get_repository_status(&Some(repository)));
+        assert!(!counts.is_dirty());
+        assert_eq!(counts.to_string(), "");
+    }
+
+    #[test]
+    fn conflicts_are_counted_first() {
+        let files = [
+            file("b.txt", None, StatusSection::Untracked, StatusChange::New),
+            file("a.txt", None, StatusSection::Conflicted, StatusChange::Conflicted),
+        ];
+        assert_eq!(StatusCounts::from_files(&files).to_string(), "!1 ?1");
+        assert_eq!(get_repository_status(&None), []);
+    }
+}
@@ -0,0 +1,76 @@
+use crate::git_operations::repo::git_credentials_callback;
+use git2::build::CheckoutBuilder;
+use git2::{FetchOptions, RemoteCallbacks, Repository, SubmoduleUpdateOptions};
+use std::path::PathBuf;
+
+/// Whether the checked-out commit of a submodule matches the one recorded in the superproject
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubmoduleState {
+    Uninitialized,
+    Matching,
+    Modified,
+}
+
+pub struct SubmoduleInfo {
+    pub name: String,
+    pub path: PathBuf,
+    pub state: SubmoduleState,
+}
+
+pub fn get_repository_submodules(repository: &Option<Repository>) -> Vec<SubmoduleInfo> {
+    let mut submodules = Vec::new();
+    if let Some(r) = repository {
+        let workdir = match r.workdir() {
+            Some(w) => w,
+            None => return submodules,
+        };
+
+        if let Ok(list) = r.submodules() {
+            list.iter().for_each(|s| {
+                let state = match (s.head_id(), s.workdir_id()) {
+                    (_, None) => SubmoduleState::Uninitialized,
+                    (Some(h), Some(w)) if h == w => SubmoduleState::Matching,
+                    _ => SubmoduleState::Modified,
+                };
+                submodules.push(SubmoduleInfo {
+                    name: s.name().unwrap_or_default().to_string(),
+                    path: workdir.join(s.path()),
+                    state,
+                });
+            });
+        }
+    }
+    submodules
+}
+
+pub fn init_submodules(repository: &Repository) -> Result<String, git2::Error> {
+    for mut s in repository.submodules()? {
+        s.init(false)?;
+    }
+    Ok(String::from("Submodules are initialized!"))
+}
+
+pub fn update_submodules(repository: &Repository) -> Result<String, git2::Error> {
+    for mut s in repository.submodules()? {
+        let mut cb = RemoteCallbacks::new();
+        cb.credentials(git_credentials_callback);
+        let mut fo = FetchOptions::new();
+        fo.remote_callbacks(cb);
+
+        let mut checkout = CheckoutBuilder::new();
+        checkout.safe();
+
+        let mut opts = SubmoduleUpdateOptions::new();
+        opts.fetch(fo);
+        opts.checkout(checkout);
+        s.update(true, Some(&mut opts))?;
+    }
+    Ok(String::from("Submodules are updated!"))
+}
+
+pub fn sync_submodules(repository: &Repository) -> Result<String, git2::Error> {
+    for mut s in repository.submodules()? {
+        s.sync()?;
+    }
+    Ok(String::from("Submodules are synchronized!"))
+}
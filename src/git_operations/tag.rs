@@ -0,0 +1,125 @@
+use crate::git_operations::sign::{should_sign, sign_buffer};
+use git2::{ObjectType, Oid, Repository, Signature};
+
+/// Creates an annotated tag on the commit HEAD is on, replacing a tag with the same name. The tag
+/// is signed when `tag.gpgsign` is set.
+pub fn create_tag(repository: &Repository, name: &str, message: &str) -> Result<String, git2::Error> {
+    // HEAD is tagged whether it is on a branch or detached, an unborn branch has nothing to tag
+    let target = repository
+        .head()
+        .and_then(|h| h.peel(ObjectType::Commit))
+        .map_err(|_| git2::Error::from_str("HEAD has no commit to tag"))?;
+    let tagger = repository.signature()?;
+
+    if !should_sign(repository, "tag.gpgsign") {
+        repository.tag(name, &target, &tagger, message, true)?;
+        return Ok(String::from("Tag creation is successful!"));
+    }
+
+    // libgit2 can't sign tags, the tag object is written the way git writes a signed one: with the
+    // signature of the object appended to its message
+    let message = git2::message_prettify(message, None)?;
+    let buffer = tag_buffer(target.id(), name, &tagger, &message);
+    let signature = sign_buffer(repository, &tagger, &buffer)?;
+    let oid = repository
+        .odb()?
+        .write(ObjectType::Tag, format!("{}{}", buffer, signature).as_bytes())?;
+    repository.reference(&format!("refs/tags/{}", name), oid, true, "tag: signed")?;
+    Ok(String::from("Signed tag creation is successful!"))
+}
+
+/// Tag object as git writes it, without a signature
+fn tag_buffer(target: Oid, name: &str, tagger: &Signature, message: &str) -> String {
+    format!(
+        "object {}\ntype commit\ntag {}\ntagger {}\n\n{}",
+        target,
+        name,
+        format_signature(tagger),
+        message
+    )
+}
+
+/// Signature as it is written in a git object, like `Jane <jane@example.com> 1700000000 +0100`
+fn format_signature(signature: &Signature) -> String {
+    let when = signature.when();
+    let offset = when.offset_minutes();
+    format!(
+        "{} <{}> {} {}{:02}{:02}",
+        signature.name().unwrap_or_default(),
+        signature.email().unwrap_or_default(),
+        when.seconds(),
+        if offset < 0 { '-' } else { '+' },
+        offset.abs() / 60,
+        offset.abs() % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git_operations::test_utils::{commit_all, init_repository, write_file};
+    use git2::Time;
+
+    #[test]
+    fn signature_is_formatted_like_git() {
+        let east = Signature::new("Jane", "jane@example.com", &Time::new(1700000000, 330)).unwrap();
+        assert_eq!(format_signature(&east), "Jane <jane@example.com> 1700000000 +0530");
+        let west = Signature::new("Jane", "jane@example.com", &Time::new(1700000000, -90)).unwrap();
+        assert_eq!(format_signature(&west), "Jane <jane@example.com> 1700000000 -0130");
+    }
+
+    #[test]
+    fn tag_buffer_is_laid_out_like_git() {
+        let tagger = Signature::new("Jane", "jane@example.com", &Time::new(1700000000, 0)).unwrap();
+        let target = Oid::from_str("0123456789abcdef0123456789abcdef01234567").unwrap();
+        assert_eq!(
+            tag_buffer(target, "v1.0", &tagger, "Release\n"),
+            "object 0123456789abcdef0123456789abcdef01234567\n\
+             type commit\n\
+             tag v1.0\n\
+             tagger Jane <jane@example.com> 1700000000 +0000\n\
+             \n\
+             Release\n"
+        );
+    }
+
+    #[test]
+    fn head_is_tagged() {
+        let (_dir, repository) = init_repository();
+        assert!(create_tag(&repository, "v1.0", "Release").is_err());
+        write_file(&repository, "a.txt", "a\n");
+        commit_all(&repository, "init");
+
+        create_tag(&repository, "v1.0", "Release").unwrap();
+        let tag = repository.revparse_single("v1.0").unwrap().peel_to_tag().unwrap();
+        assert_eq!(tag.target_id(), repository.head().unwrap().target().unwrap());
+        assert_eq!(tag.message(), Some("Release"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn signature_is_appended_to_the_tag() {
+        use std::os::unix::fs::PermissionsExt;
+        let (dir, repository) = init_repository();
+        write_file(&repository, "a.txt", "a\n");
+        commit_all(&repository, "init");
+        // Stands in for gpg, the signature is the same whatever is signed
+        let gpg = dir.path().join("gpg.sh");
+        std::fs::write(&gpg, "#!/bin/sh\ncat > /dev/null\nprintf 'SIGNATURE\\n'\n").unwrap();
+        std::fs::set_permissions(&gpg, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let mut config = repository.config().unwrap();
+        config.set_bool("tag.gpgsign", true).unwrap();
+        config.set_str("gpg.program", gpg.to_str().unwrap()).unwrap();
+
+        create_tag(&repository, "v1.0", "Release").unwrap();
+        let tag = repository.revparse_single("v1.0").unwrap();
+        let odb = repository.odb().unwrap();
+        let object = odb.read(tag.id()).unwrap();
+        let tagger = tag.as_tag().unwrap().tagger().unwrap();
+        let head = repository.head().unwrap().target().unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(object.data()),
+            format!("{}SIGNATURE\n", tag_buffer(head, "v1.0", &tagger, "Release\n"))
+        );
+    }
+}
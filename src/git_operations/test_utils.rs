@@ -0,0 +1,52 @@
+use git2::{Repository, Signature};
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+/// Repository in a temporary folder with an identity, removed when the folder is dropped
+pub fn init_repository() -> (TempDir, Repository) {
+    let dir = TempDir::new().unwrap();
+    let repository = Repository::init(dir.path()).unwrap();
+    let mut config = repository.config().unwrap();
+    config.set_str("user.name", "Jane").unwrap();
+    config.set_str("user.email", "jane@example.com").unwrap();
+    // Signing set up in the global config of the machine is left out
+    config.set_bool("commit.gpgsign", false).unwrap();
+    config.set_bool("tag.gpgsign", false).unwrap();
+    (dir, repository)
+}
+
+pub fn write_file(repository: &Repository, path: &str, content: &str) {
+    let path = repository.workdir().unwrap().join(path);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+/// Content of a file in the index
+pub fn read_index(repository: &Repository, path: &str) -> Option<String> {
+    let entry = repository.index().unwrap().get_path(Path::new(path), 0)?;
+    let blob = repository.find_blob(entry.id).unwrap();
+    Some(String::from_utf8_lossy(blob.content()).to_string())
+}
+
+/// Commits every file of the working tree on HEAD
+pub fn commit_all(repository: &Repository, message: &str) {
+    let mut index = repository.index().unwrap();
+    index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None).unwrap();
+    index.write().unwrap();
+    let tree = repository.find_tree(index.write_tree().unwrap()).unwrap();
+    let sig = Signature::now("Jane", "jane@example.com").unwrap();
+    let parent = repository.head().and_then(|h| h.peel_to_commit()).ok();
+    let parents: Vec<&git2::Commit> = parent.iter().collect();
+    repository.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents).unwrap();
+}
+
+/// Installs an executable hook in the hooks folder of the repository
+#[cfg(unix)]
+pub fn write_hook(repository: &Repository, name: &str, script: &str) {
+    use std::os::unix::fs::PermissionsExt;
+    let path = repository.path().join("hooks").join(name);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, script).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+}
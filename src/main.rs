@@ -7,7 +7,6 @@ use crossterm::terminal::{
 };
 use std::io;
 use std::path::Path;
-use std::time::Duration;
 use tui::{backend::CrosstermBackend, Terminal};
 
 mod run_app;
@@ -18,13 +17,56 @@ mod git_operations;
 
 mod components;
 use components::application::App;
+use components::workspaces::Workspaces;
+
+mod config;
+use config::WorkspaceConfig;
+
+mod session;
+use session::Sessions;
 
 
 fn main() -> Result<(), io::Error> {
-    let path = std::env::args().nth(1).unwrap_or_else(|| "./".to_string());
-    if !Path::new(&path).exists() {
-        panic!("Path does not exists!");
+    let mut paths = Vec::new();
+    let mut depth = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--depth" | "-d" => {
+                depth = args.next().and_then(|d| d.parse::<usize>().ok()).filter(|d| *d > 0);
+                if depth.is_none() {
+                    panic!("Depth should be a positive number!");
+                }
+            }
+            _ => paths.push(arg),
+        }
+    }
+    if paths.is_empty() {
+        paths.push("./".to_string());
+    }
+
+    let mut sessions = Sessions::load();
+    let mut workspaces = Workspaces::builder();
+    for path in paths {
+        if !Path::new(&path).exists() {
+            panic!("Path does not exists! {}", path);
+        }
+
+        let config = WorkspaceConfig::load(Path::new(&path))
+            .unwrap_or_else(|e| panic!("Could not read configuration, {}", e));
+
+        let mut builder = App::builder().path(path).config(config);
+        if let Some(depth) = depth {
+            builder = builder.depth(depth);
+        }
+        let mut app = builder.build();
+        if let Some(session) = sessions.take(&app.path) {
+            app.restore_session(session);
+        }
+        workspaces = workspaces.app(app);
     }
+    let mut workspaces = workspaces.build();
 
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -32,8 +74,7 @@ fn main() -> Result<(), io::Error> {
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let app = App::builder().path(path).build();
-    let _ = run_app(&mut terminal, app, Duration::from_millis(5000));
+    let result = run_app(&mut terminal, &mut workspaces);
 
     disable_raw_mode()?;
     execute!(
@@ -43,5 +84,15 @@ fn main() -> Result<(), io::Error> {
     )?;
     terminal.show_cursor()?;
 
+    if result.is_ok() {
+        workspaces
+            .apps
+            .iter()
+            .for_each(|app| sessions.insert(app.path.clone(), app.session()));
+        if let Err(e) = sessions.save() {
+            eprintln!("Could not save the session, {}", e);
+        }
+    }
+
     Ok(())
 }
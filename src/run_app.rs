@@ -1,33 +1,40 @@
-use crate::utility::{centered_rect, create_block, create_block_with_selection, create_block_with_title, create_selection_list_from_vector};
+use crate::utility::{
+    centered_rect, create_block, create_block_with_selection, create_block_with_selection_and_info, create_block_with_title,
+    create_selection_list_from_vector,
+};
 use crate::App;
 use crossterm::event;
-use crossterm::event::{Event, KeyCode};
+use crossterm::event::{DisableMouseCapture, EnableMouseCapture, Event, KeyCode};
+use crossterm::execute;
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
 use futures::SinkExt;
 use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
+use std::env;
+use std::path::Path;
+use std::process::{Command, ExitStatus};
 use std::{io};
 use std::time::{Duration, Instant};
 use tui::backend::Backend;
-use tui::layout::{Alignment, Constraint, Direction, Layout};
-use tui::style::{Color, Style};
-use tui::widgets::{Block, Borders, Clear, Paragraph};
+use tui::layout::{Alignment, Constraint, Direction, Layout, Rect};
+use tui::style::{Color, Modifier, Style};
+use tui::widgets::{Block, Borders, Clear, List, ListItem, Paragraph, Tabs, Wrap};
 use tui::{Frame, Terminal};
-use tui::text::{Text};
+use tui::text::{Span, Spans, Text};
 
+use crate::config::RepositorySort;
+use crate::git_operations::diff::DiffLineKind;
 use crate::git_operations::log::print_log;
+use crate::git_operations::sign::terminal_was_suspended;
 
 use crate::components::{
+    filter::RepositoryFilter,
     logs::Logs,
     modes::InputMode,
     selection::Selection,
+    workspaces::Workspaces,
 };
 
-pub fn run_app<B: Backend>(
-    terminal: &mut Terminal<B>,
-    mut app: App,
-    tick_rate: Duration,
-) -> io::Result<()> {
-    let mut last_tick = Instant::now();
-
+fn create_watcher(app: &App) -> RecommendedWatcher {
     let mut channels = app.channels.0.clone();
 
     let mut watcher = RecommendedWatcher::new(move |res| {
@@ -41,46 +48,117 @@ pub fn run_app<B: Backend>(
         .watch(app.path.as_ref(), RecursiveMode::Recursive)
         .unwrap();
 
+    watcher
+}
+
+pub fn run_app<B: Backend>(
+    terminal: &mut Terminal<B>,
+    workspaces: &mut Workspaces,
+) -> io::Result<()> {
+    let mut last_tick = Instant::now();
+
+    // Every workspace has its own watcher, they are kept alive until the application quits
+    let _watchers: Vec<RecommendedWatcher> = workspaces.apps.iter().map(create_watcher).collect();
+
     loop {
-        if let Ok(Some(Ok(event))) = &app.channels.1.try_next() {
-            app.update_application_content(event.paths.get(0).unwrap());
-        };
+        // A signing program may have been given the terminal to ask for a passphrase
+        if terminal_was_suspended() {
+            terminal.clear()?;
+        }
+        workspaces.apps.iter_mut().for_each(|app| {
+            // A single git command changes many files, every pending event is handled before drawing
+            let mut paths = Vec::new();
+            while let Ok(Some(event)) = app.channels.1.try_next() {
+                paths.extend(event.map(|e| e.paths).unwrap_or_default());
+            }
+            if !paths.is_empty() {
+                app.update_changed_paths(paths);
+            }
+            app.poll_clones();
+        });
 
         terminal.draw(|f| {
-            ui(f, &mut app);
+            let size = f.size();
+            let area = if workspaces.apps.len() > 1 {
+                let chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Length(1), Constraint::Min(0)])
+                    .split(size);
+                let tabs = Tabs::new(workspaces.titles().into_iter().map(Spans::from).collect())
+                    .select(workspaces.index)
+                    .highlight_style(Style::default().bg(Color::White).fg(Color::Black));
+                f.render_widget(tabs, chunks[0]);
+                chunks[1]
+            } else {
+                size
+            };
+            ui(f, workspaces.active(), area);
         })?;
 
-        let timeout = tick_rate
+        let tick_rate = Duration::from_millis(workspaces.active().config.tick_rate());
+
+        // Changes seen by the watchers are picked up without waiting for the next tick
+        let mut timeout = tick_rate
             .checked_sub(last_tick.elapsed())
-            .unwrap_or_else(|| Duration::from_secs(0));
+            .unwrap_or_else(|| Duration::from_secs(0))
+            .min(WATCH_INTERVAL);
+        // Redraw often while background jobs report progress
+        if workspaces.apps.iter().any(|a| !a.clones.is_empty()) {
+            timeout = timeout.min(Duration::from_millis(100));
+        }
 
         if event::poll(timeout)? {
             if let Event::Key(key) = event::read()? {
+                let app = workspaces.active();
                 match app.input_mode {
                     InputMode::Normal => match key.code {
                         KeyCode::Char('q') => {
-                            drop(app.channels.1);
                             return Ok(());
                         },
+                        KeyCode::Tab => workspaces.next(),
+                        KeyCode::BackTab => workspaces.previous(),
                         KeyCode::Left => app.repositories.unselect(),
+                        KeyCode::Enter if app.selection == Selection::Repositories => app.toggle_selected_item(),
+                        KeyCode::Enter if app.selection == Selection::Stash => app.show_stash_diff(),
+                        KeyCode::Char('n') => app.toggle_non_repositories(),
+                        KeyCode::Char('o') => app.toggle_sort(),
+                        KeyCode::Char(c @ '0'..='5') => app.set_filter(RepositoryFilter::from_key(c).unwrap_or_default()),
                         KeyCode::Down => app.next(),
                         KeyCode::Up => app.previous(),
-                        KeyCode::Char('l') => {
-                            if app.repositories.state.selected().is_some() && app.get_selected_repository().is_repository {
-                                app.input_mode = InputMode::Logs
-                            }
+                        KeyCode::Char('l') if app.repositories.state.selected().is_some() && app.get_selected_repository().is_repository => {
+                            app.input_mode = InputMode::Logs
                         },
+                        KeyCode::Char('d') if app.repositories.state.selected().is_some() => app.show_diff(),
+                        KeyCode::Char('c') if app.repositories.state.selected().is_some() => {
+                            if let Some(path) = app.prepare_commit_message() {
+                                let editor = run_editor(terminal, &path);
+                                app.commit_from_editor(&path, editor);
+                            }
+                        }
+                        KeyCode::Char(c @ ('a' | 'u' | 'A' | 'U'))
+                            if app.selection == Selection::Status && app.repositories.state.selected().is_some() =>
+                        {
+                            app.update_index(c.eq_ignore_ascii_case(&'a'), c.is_ascii_uppercase())
+                        }
+                        KeyCode::Char('x') if app.selection == Selection::Status && app.repositories.state.selected().is_some() => {
+                            app.preview_discard()
+                        }
                         KeyCode::Char('r') => app.change_selection(Selection::Repositories),
                         KeyCode::Char('t') => app.change_selection(Selection::Tags),
                         KeyCode::Char('b') => app.change_selection(Selection::Branches),
+                        KeyCode::Char('s') => app.change_selection(Selection::Status),
+                        KeyCode::Char('h') => app.change_selection(Selection::Stash),
                         KeyCode::Char(':') => {
-                            if app.repositories.state.selected().is_some() && app.get_selected_repository().is_repository {
+                            if app.selection == Selection::Repositories
+                                || app.repositories.state.selected().is_some() && app.get_selected_repository().is_repository
+                            {
                                 app.input_mode = InputMode::Editing;
                             } else {
                                 app.add_log("Repository Should be selected".to_string())
                             }
                         }
                         KeyCode::Char('/') => {
+                            app.input = app.last_search.clone();
                             app.input_mode = InputMode::Search;
                         }
                         KeyCode::Char('$') => {
@@ -140,19 +218,87 @@ pub fn run_app<B: Backend>(
                         KeyCode::Char('q') | KeyCode::Char('l') | KeyCode::Esc => { app.reset_input(); },
                         _ => {}
                     }
+                    InputMode::Diff => {
+                        if let Some(diff) = &mut app.diff {
+                            match key.code {
+                                KeyCode::Down => diff.move_down(1),
+                                KeyCode::Up => diff.move_up(1),
+                                KeyCode::PageDown => diff.move_down(DIFF_PAGE),
+                                KeyCode::PageUp => diff.move_up(DIFF_PAGE),
+                                KeyCode::Right => diff.scroll_right(),
+                                KeyCode::Left => diff.scroll_left(),
+                                KeyCode::Char('n') | KeyCode::Tab => diff.next_file(),
+                                KeyCode::Char('p') | KeyCode::BackTab => diff.previous_file(),
+                                KeyCode::Char(']') => diff.next_hunk(),
+                                KeyCode::Char('[') => diff.previous_hunk(),
+                                KeyCode::Char(' ') => diff.toggle_mark(),
+                                KeyCode::Char('v') => app.switch_diff_view(),
+                                KeyCode::Char('a') | KeyCode::Char('u') => app.apply_diff_hunk(),
+                                KeyCode::Char('q') | KeyCode::Char('d') | KeyCode::Esc => app.close_diff(),
+                                _ => {}
+                            }
+                        }
+                    }
+                    InputMode::Confirm => match key.code {
+                        KeyCode::Down => {
+                            if let Some(confirm) = &mut app.confirm {
+                                confirm.scroll_down()
+                            }
+                        }
+                        KeyCode::Up => {
+                            if let Some(confirm) = &mut app.confirm {
+                                confirm.scroll_up()
+                            }
+                        }
+                        KeyCode::Char('y') => app.confirm_action(),
+                        KeyCode::Char('n') | KeyCode::Char('q') | KeyCode::Esc => app.cancel_confirm(),
+                        _ => {}
+                    },
                 }
             }
         }
 
         if last_tick.elapsed() >= tick_rate {
-            app.on_tick();
+            workspaces.apps.iter_mut().for_each(|app| app.on_tick());
             last_tick = Instant::now()
         }
     }
 }
 
-fn ui<'a, B: Backend>(f: &'a mut Frame<B>, app: &'a mut App) {
-    let size = f.size();
+/// Opens `$EDITOR` (`vi` when unset) on a file with the terminal given back to it, then restores the TUI
+fn run_editor<B: Backend>(terminal: &mut Terminal<B>, path: &Path) -> io::Result<ExitStatus> {
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture)?;
+
+    let editor = env::var("EDITOR").unwrap_or_else(|_| String::from("vi"));
+    let mut words = editor.split_whitespace();
+    let status = Command::new(words.next().unwrap_or("vi")).args(words).arg(path).status();
+
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)?;
+    terminal.clear()?;
+    status
+}
+
+/// Longest wait for a key before the events of the watchers are handled
+const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Lines scrolled by page up and page down in the diff popup
+const DIFF_PAGE: usize = 20;
+
+/// Active filter and sort of the repository list shown next to its title
+fn repositories_info(app: &App) -> Option<String> {
+    let mut info = Vec::new();
+    if app.filter != RepositoryFilter::All {
+        info.push(format!("filter: {}", app.filter));
+    }
+    if app.sort != RepositorySort::Name {
+        info.push(app.sort.to_string());
+    }
+    Some(info.join(" | ")).filter(|i| !i.is_empty())
+}
+
+fn ui<'a, B: Backend>(f: &'a mut Frame<B>, app: &'a mut App, size: Rect) {
 
     // Big chunk divides screen for part and bottom info
     let chunks = Layout::default()
@@ -171,21 +317,32 @@ fn ui<'a, B: Backend>(f: &'a mut Frame<B>, app: &'a mut App) {
         .constraints([Constraint::Percentage(80), Constraint::Percentage(20)])
         .split(main_chunks[0]);
 
+    let repository_block = create_block_with_selection_and_info(
+        app,
+        Selection::Repositories,
+        repositories_info(app),
+    );
+    let (repositories, repositories_state) = app.repositories.view();
     let repository_list = create_selection_list_from_vector(
-        &app.repositories.items,
-        create_block_with_selection(app, Selection::Repositories),
+        repositories,
+        repository_block,
         Some(&left_chunks[0]),
     );
-    f.render_stateful_widget(repository_list, left_chunks[0], &mut app.repositories.state);
+    f.render_stateful_widget(repository_list, left_chunks[0], repositories_state);
 
     let log_list =
         create_selection_list_from_vector(&app.logs.items, create_block_with_title("Logs"), None);
     f.render_stateful_widget(log_list, left_chunks[1], &mut app.logs.state);
 
-    //Branches and Tags screens
+    //Branches, Tags, Status and Stash screens
     let right_chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .constraints([
+            Constraint::Percentage(25),
+            Constraint::Percentage(25),
+            Constraint::Percentage(30),
+            Constraint::Percentage(20),
+        ])
         .split(main_chunks[1]);
 
     // Tags
@@ -204,8 +361,29 @@ fn ui<'a, B: Backend>(f: &'a mut Frame<B>, app: &'a mut App) {
     );
     f.render_stateful_widget(branch_list, right_chunks[1], &mut app.branches.state);
 
+    // Status
+    let status_list = create_selection_list_from_vector(
+        &app.status.items,
+        create_block_with_selection(app, Selection::Status),
+        None,
+    );
+    f.render_stateful_widget(status_list, right_chunks[2], &mut app.status.state);
+
+    // Stash
+    let stash_list = create_selection_list_from_vector(
+        &app.stashes.items,
+        create_block_with_selection(app, Selection::Stash),
+        None,
+    );
+    f.render_stateful_widget(stash_list, right_chunks[3], &mut app.stashes.state);
+
     if app.input_mode == InputMode::Logs {
-        let block = Block::default().title("Logs").borders(Borders::ALL);
+        let title = app
+            .repository_logs
+            .as_ref()
+            .and_then(|l| l.title.clone())
+            .unwrap_or_else(|| "Logs".to_string());
+        let block = Block::default().title(title).borders(Borders::ALL);
         let area = centered_rect(90, 90, size);
         f.render_widget(Clear, area); //this clears out the background
 
@@ -233,9 +411,27 @@ fn ui<'a, B: Backend>(f: &'a mut Frame<B>, app: &'a mut App) {
         app.repository_logs = None;
     }
 
+    if app.input_mode == InputMode::Diff {
+        draw_diff(f, app, size);
+    }
+
+    if let (InputMode::Confirm, Some(confirm)) = (&app.input_mode, &app.confirm) {
+        let block = Block::default().title(confirm.title.clone()).borders(Borders::ALL);
+        let area = centered_rect(60, 60, size);
+        f.render_widget(Clear, area);
+        f.render_widget(
+            Paragraph::new(Text::from(confirm.lines.join("\n")))
+                .scroll((confirm.offset, 0))
+                .wrap(Wrap { trim: false })
+                .block(block),
+            area,
+        );
+    }
+
     // Info at the bottom
-    let help = match app.repositories.state.selected() {
-        Some(_) => app.generate_help(),
+    let help = match (app.progress_info(), app.repositories.state.selected()) {
+        (Some(progress), _) => progress,
+        (None, Some(_)) => app.generate_help(),
         _ => String::new(),
     };
 
@@ -262,6 +458,73 @@ fn ui<'a, B: Backend>(f: &'a mut Frame<B>, app: &'a mut App) {
             .style(Style::default().bg(Color::White).fg(Color::Black))
             .block(create_block())
             .alignment(Alignment::Left),
+        InputMode::Diff => Paragraph::new(
+            "↑↓ ←→ PgUp PgDn to move | n/p next/previous file | ]/[ next/previous hunk | v to switch view | space to mark a line | a/u to stage/unstage the hunk or the marked lines | q",
+        )
+            .style(Style::default().bg(Color::White).fg(Color::Black))
+            .block(create_block())
+            .alignment(Alignment::Left),
+        InputMode::Confirm => Paragraph::new("y to confirm | n to cancel | ↑↓ to scroll")
+            .style(Style::default().bg(Color::White).fg(Color::Black))
+            .block(create_block())
+            .alignment(Alignment::Left),
     };
     f.render_widget(info, chunks[1]);
 }
+
+/// Diff popup, the jump list of the files on the left and the colored diff on the right
+fn draw_diff<B: Backend>(f: &mut Frame<B>, app: &mut App, size: Rect) {
+    let diff = match &mut app.diff {
+        Some(d) => d,
+        None => return,
+    };
+
+    let area = centered_rect(90, 90, size);
+    f.render_widget(Clear, area);
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(25), Constraint::Percentage(75)])
+        .split(area);
+
+    let files: Vec<ListItem> = diff.diff.files.iter().map(|d| ListItem::new(d.path.clone())).collect();
+    let files = List::new(files)
+        .block(Block::default().title("Files").borders(Borders::ALL))
+        .highlight_style(Style::default().bg(Color::White).fg(Color::Black));
+    f.render_stateful_widget(files, chunks[0], &mut diff.files);
+
+    // Borders take two lines
+    diff.height = chunks[1].height.saturating_sub(2);
+    let current_hunk = diff.current_hunk();
+    let lines: Vec<Spans> = diff
+        .diff
+        .lines
+        .iter()
+        .enumerate()
+        // Only the lines in view are drawn, the scroll of a paragraph can't go past u16::MAX
+        .skip(diff.offset.0)
+        .take(usize::from(diff.height))
+        .map(|(i, l)| {
+            let mut style = match l.kind {
+                DiffLineKind::File => Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+                DiffLineKind::Hunk => Style::default().fg(Color::Cyan),
+                DiffLineKind::Added => Style::default().fg(Color::Green),
+                DiffLineKind::Removed => Style::default().fg(Color::Red),
+                DiffLineKind::Context => Style::default(),
+            };
+            if i == diff.cursor {
+                style = style.bg(Color::DarkGray);
+            }
+            if diff.marked.contains(&i) {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
+            // The hunk under the cursor is marked in the gutter
+            let gutter = if l.hunk.is_some() && l.hunk == current_hunk { "▌" } else { " " };
+            Spans::from(vec![Span::raw(gutter), Span::styled(l.text.clone(), style)])
+        })
+        .collect();
+    let title = format!("Diff ({}) - {}", diff.view, diff.title);
+    let paragraph = Paragraph::new(Text::from(lines))
+        .scroll((0, diff.offset.1))
+        .block(Block::default().title(title).borders(Borders::ALL));
+    f.render_widget(paragraph, chunks[1]);
+}
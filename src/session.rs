@@ -0,0 +1,59 @@
+use crate::components::selection::Selection;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// How many of the last log lines are kept for the next launch
+pub const SESSION_LOG_LINES: usize = 100;
+
+/// State of a workspace saved when quitting and restored on the next launch
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionState {
+    pub selected_repository: Option<PathBuf>,
+    pub selection: Selection,
+    pub search: String,
+    pub logs: Vec<String>,
+}
+
+/// Session states of every workspace opened, keyed by the workspace path
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Sessions {
+    pub workspaces: BTreeMap<String, SessionState>,
+}
+
+impl Sessions {
+    /// Location of the state file, under the XDG state directory when there is one
+    fn file_path() -> Option<PathBuf> {
+        dirs::state_dir()
+            .or_else(dirs::data_local_dir)
+            .map(|d| d.join("gitten").join("state.toml"))
+    }
+
+    /// Loads the saved sessions, a missing or unreadable state file gives no sessions
+    pub fn load() -> Sessions {
+        Sessions::file_path()
+            .and_then(|p| fs::read_to_string(p).ok())
+            .and_then(|content| toml::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Sessions::file_path().ok_or_else(|| "No state directory".to_string())?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let content = toml::to_string(self).map_err(|e| e.to_string())?;
+        fs::write(path, content).map_err(|e| e.to_string())
+    }
+
+    pub fn take(&mut self, workspace: &str) -> Option<SessionState> {
+        self.workspaces.remove(workspace)
+    }
+
+    pub fn insert(&mut self, workspace: String, state: SessionState) {
+        self.workspaces.insert(workspace, state);
+    }
+}
@@ -14,7 +14,11 @@ fn convert_vector_to_list_item_vector<'a, T: Display + ConvertableToListItem>(
     iterator: &'a [T],
     r: Option<&'a Rect>,
 ) -> Vec<ListItem<'a>> {
-    iterator.iter().map(|f| f.convert_to_list_item(r)).collect()
+    iterator
+        .iter()
+        .filter(|f| f.is_visible())
+        .map(|f| f.convert_to_list_item(r))
+        .collect()
 }
 
 pub fn create_selection_list_from_vector<'a, T: Display + ConvertableToListItem>(
@@ -32,6 +36,11 @@ pub fn create_selection_list_from_vector<'a, T: Display + ConvertableToListItem>
 }
 
 pub fn create_block_with_selection(app: &App, selection: Selection) -> Block<'static> {
+    create_block_with_selection_and_info(app, selection, None)
+}
+
+/// Block of a selection with extra information, like the active sort, next to its title
+pub fn create_block_with_selection_and_info(app: &App, selection: Selection, info: Option<String>) -> Block<'static> {
     let b = Block::default();
 
     let style = if app.selection == selection {
@@ -43,10 +52,11 @@ pub fn create_block_with_selection(app: &App, selection: Selection) -> Block<'st
         Style::default().bg(Color::Black).fg(Color::White)
     };
 
-    b.borders(Borders::ALL).title(Spans::from(vec![Span::styled(
-        selection.to_string(),
-        style,
-    )]))
+    let mut title = vec![Span::styled(selection.to_string(), style)];
+    if let Some(info) = info {
+        title.push(Span::raw(format!(" {} ", info)));
+    }
+    b.borders(Borders::ALL).title(Spans::from(title))
 }
 
 pub fn create_block_with_title(title: &str) -> Block<'static> {